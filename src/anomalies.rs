@@ -0,0 +1,218 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::excel_merger::{excel_to_date, EmployeeData};
+use crate::privacy::Privacy;
+
+// Zwolnienie lekarskie może trwać najwyżej 182 dni, więc daty dalej w przyszłości są podejrzane
+pub const FUTURE_LIMIT_DAYS: i64 = 182;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnomalyRule {
+    MissingDates,
+    InvertedDates,
+    Overlapping,
+    BeforeEmployment,
+    FarFuture,
+}
+
+impl AnomalyRule {
+    pub fn id(&self) -> &'static str {
+        match self {
+            AnomalyRule::MissingDates => "A01",
+            AnomalyRule::InvertedDates => "A02",
+            AnomalyRule::Overlapping => "A03",
+            AnomalyRule::BeforeEmployment => "A04",
+            AnomalyRule::FarFuture => "A05",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnomalyRule::MissingDates => "Brak lub niepoprawna data",
+            AnomalyRule::InvertedDates => "Data do przed datą od",
+            AnomalyRule::Overlapping => "Nakładające się zaświadczenia",
+            AnomalyRule::BeforeEmployment => "Zaświadczenie przed datą zatrudnienia",
+            AnomalyRule::FarFuture => "Data w odległej przyszłości",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub rule: AnomalyRule,
+    // Indeks wiersza w danych przekazanych do `check`
+    pub index: usize,
+    pub opis: String,
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%d.%m.%Y").to_string()
+}
+
+pub fn check(data: &[EmployeeData], today: NaiveDate) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    let future_limit = today + Duration::days(FUTURE_LIMIT_DAYS);
+
+    for (index, employee) in data.iter().enumerate() {
+        let (Some(start), Some(end)) = (
+            employee.data_od.and_then(excel_to_date),
+            employee.data_do.and_then(excel_to_date),
+        ) else {
+            anomalies.push(Anomaly {
+                rule: AnomalyRule::MissingDates,
+                index,
+                opis: "Nie udało się odczytać daty od lub daty do".to_string(),
+            });
+            continue;
+        };
+
+        if end < start {
+            anomalies.push(Anomaly {
+                rule: AnomalyRule::InvertedDates,
+                index,
+                opis: format!("Okres {} - {} kończy się przed rozpoczęciem", format_date(start), format_date(end)),
+            });
+        }
+
+        if let Some(zatrudnienie) = employee.data_zatrudnienia.and_then(excel_to_date) {
+            if start < zatrudnienie {
+                anomalies.push(Anomaly {
+                    rule: AnomalyRule::BeforeEmployment,
+                    index,
+                    opis: format!(
+                        "Zwolnienie od {} zaczyna się przed zatrudnieniem ({})",
+                        format_date(start),
+                        format_date(zatrudnienie)
+                    ),
+                });
+            }
+        }
+
+        let latest = start.max(end);
+        if latest > future_limit {
+            anomalies.push(Anomaly {
+                rule: AnomalyRule::FarFuture,
+                index,
+                opis: format!(
+                    "Data {} jest ponad {} dni od dzisiaj",
+                    format_date(latest),
+                    FUTURE_LIMIT_DAYS
+                ),
+            });
+        }
+    }
+
+    anomalies.extend(check_overlapping(data));
+    anomalies.sort_by_key(|anomaly| (anomaly.index, anomaly.rule));
+    anomalies
+}
+
+// Pary nieanulowanych zaświadczeń tej samej osoby, których okresy mają wspólne dni
+fn check_overlapping(data: &[EmployeeData]) -> Vec<Anomaly> {
+    let mut by_pesel: HashMap<&str, Vec<(usize, NaiveDate, NaiveDate)>> = HashMap::new();
+    for (index, employee) in data.iter().enumerate() {
+        if employee.is_cancelled() {
+            continue;
+        }
+        if let Some((start, end)) = employee.okres() {
+            if start <= end {
+                by_pesel.entry(&employee.pesel).or_default().push((index, start, end));
+            }
+        }
+    }
+
+    let mut anomalies = Vec::new();
+    for periods in by_pesel.values() {
+        for (pos, &(index, start, end)) in periods.iter().enumerate() {
+            for &(other, other_start, other_end) in periods.iter().skip(pos + 1) {
+                if start > other_end || other_start > end {
+                    continue;
+                }
+                anomalies.push(overlap(data, index, other, other_start, other_end));
+                anomalies.push(overlap(data, other, index, start, end));
+            }
+        }
+    }
+    anomalies
+}
+
+fn overlap(data: &[EmployeeData], index: usize, other: usize, other_start: NaiveDate, other_end: NaiveDate) -> Anomaly {
+    Anomaly {
+        rule: AnomalyRule::Overlapping,
+        index,
+        opis: format!(
+            "Okres nakłada się z zaświadczeniem {} ({} - {}, wiersz {})",
+            data[other].nr_zaswiadczenia,
+            format_date(other_start),
+            format_date(other_end),
+            data[other].wiersz
+        ),
+    }
+}
+
+pub fn summary(anomalies: &[Anomaly]) -> BTreeMap<AnomalyRule, usize> {
+    let mut counts = BTreeMap::new();
+    for anomaly in anomalies {
+        *counts.entry(anomaly.rule).or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    anomalies: &[Anomaly],
+    data: &[EmployeeData],
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Anomalie")?;
+
+    let headers = [
+        "Reguła",
+        "Opis reguły",
+        "Wyjaśnienie",
+        "Wiersz L4",
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Seria i nr zaśw.",
+        "Data od",
+        "Data do",
+        "Status zaśw.",
+    ];
+    let widths = [8.0, 34.0, 60.0, 10.0, 20.0, 15.0, 12.0, 16.0, 12.0, 12.0, 12.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, anomaly) in anomalies.iter().enumerate() {
+        let row = idx as u32 + 1;
+        let employee = &data[anomaly.index];
+        sheet.write_string(row, 0, anomaly.rule.id())?;
+        sheet.write_string(row, 1, anomaly.rule.name())?;
+        sheet.write_string(row, 2, &anomaly.opis)?;
+        sheet.write_number(row, 3, employee.wiersz as f64)?;
+        sheet.write_string(row, 4, privacy.name(&employee.nazwisko))?;
+        sheet.write_string(row, 5, privacy.name(&employee.imie))?;
+        sheet.write_string(row, 6, privacy.pesel(&employee.pesel))?;
+        sheet.write_string(row, 7, &employee.nr_zaswiadczenia)?;
+        match employee.data_od {
+            Some(excel_date) => sheet.write_number_with_format(row, 8, excel_date, date_format)?,
+            None => sheet.write_blank(row, 8, date_format)?,
+        };
+        match employee.data_do {
+            Some(excel_date) => sheet.write_number_with_format(row, 9, excel_date, date_format)?,
+            None => sheet.write_blank(row, 9, date_format)?,
+        };
+        sheet.write_string(row, 10, &employee.status)?;
+    }
+
+    sheet.autofilter(0, 0, anomalies.len() as u32, headers.len() as u16 - 1)?;
+    Ok(())
+}
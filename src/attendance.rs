@@ -0,0 +1,172 @@
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rust_xlsxwriter::utility::row_col_to_cell;
+use rust_xlsxwriter::{Color, Format, FormatAlign, FormatBorder, Formula, Workbook};
+use std::collections::BTreeMap;
+
+use crate::calendar::{self, MONTH_NAMES, WEEKDAY_NAMES};
+use crate::excel_merger::{is_yes, EmployeeData};
+use crate::privacy::Privacy;
+
+// Kody nieobecności stosowane na listach obecności
+pub const CODE_ILLNESS: &str = "C";
+pub const CODE_CARE: &str = "O";
+pub const CODE_HOSPITAL: &str = "S";
+
+const CODES: [&str; 3] = [CODE_ILLNESS, CODE_CARE, CODE_HOSPITAL];
+const FIRST_DAY_COL: u16 = 3;
+
+pub fn absence_code(employee: &EmployeeData) -> &'static str {
+    if is_yes(&employee.pobyt_w_szpitalu) {
+        CODE_HOSPITAL
+    } else if employee.is_care() {
+        CODE_CARE
+    } else {
+        CODE_ILLNESS
+    }
+}
+
+// Pobyt w szpitalu ma pierwszeństwo przed opieką, a opieka przed zwykłą chorobą,
+// gdy w tym samym dniu obowiązuje kilka zaświadczeń
+fn code_priority(code: &str) -> u8 {
+    match code {
+        CODE_HOSPITAL => 3,
+        CODE_CARE => 2,
+        _ => 1,
+    }
+}
+
+struct AttendanceRow<'a> {
+    employee: &'a EmployeeData,
+    codes: Vec<Option<&'static str>>,
+}
+
+fn build_rows<'a>(data: &'a [EmployeeData], days: &[NaiveDate]) -> Vec<AttendanceRow<'a>> {
+    let mut rows: BTreeMap<(&str, &str, &str), AttendanceRow> = BTreeMap::new();
+
+    for employee in data.iter().filter(|employee| !employee.is_cancelled()) {
+        let row = rows
+            .entry((&employee.nazwisko, &employee.imie, &employee.pesel))
+            .or_insert_with(|| AttendanceRow { employee, codes: vec![None; days.len()] });
+
+        let Some((start, end)) = employee.okres() else {
+            continue;
+        };
+        let code = absence_code(employee);
+        for (idx, day) in days.iter().enumerate() {
+            if *day < start || *day > end {
+                continue;
+            }
+            let slot = &mut row.codes[idx];
+            if slot.is_none_or(|current| code_priority(code) > code_priority(current)) {
+                *slot = Some(code);
+            }
+        }
+    }
+
+    rows.into_values().collect()
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    data: &[EmployeeData],
+    year: i32,
+    month: u32,
+    privacy: &Privacy,
+    header_format: &Format,
+) -> Result<()> {
+    let days = calendar::month_days(year, month);
+    let rows = build_rows(data, &days);
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(format!("Obecność {:02}-{}", month, year))?;
+
+    let title_format = Format::new().set_bold().set_font_size(14);
+    let day_header_format = header_format.clone().set_align(FormatAlign::Center).set_text_wrap();
+    let cell_format = Format::new()
+        .set_align(FormatAlign::Center)
+        .set_border(FormatBorder::Thin)
+        .set_border_color(Color::RGB(0xD9D9D9));
+    let free_day_format = cell_format.clone().set_background_color(Color::RGB(0xE7E6E6));
+    let code_format = cell_format.clone().set_bold().set_font_color(Color::RGB(0xC00000));
+    let free_day_code_format = free_day_format.clone().set_bold().set_font_color(Color::RGB(0xC00000));
+
+    sheet.write_string_with_format(
+        0,
+        0,
+        format!("Lista obecności - {} {}", MONTH_NAMES[month as usize - 1], year),
+        &title_format,
+    )?;
+
+    let header_row = 2;
+    for (col, header) in ["Nazwisko", "Imię", "PESEL"].iter().enumerate() {
+        sheet.write_string_with_format(header_row, col as u16, *header, header_format)?;
+    }
+    sheet.set_column_width(0, 20.0)?;
+    sheet.set_column_width(1, 15.0)?;
+    sheet.set_column_width(2, 12.0)?;
+
+    for (idx, day) in days.iter().enumerate() {
+        let col = FIRST_DAY_COL + idx as u16;
+        let label = format!("{}\n{}", day.day(), WEEKDAY_NAMES[day.weekday().num_days_from_monday() as usize]);
+        sheet.write_string_with_format(header_row, col, label, &day_header_format)?;
+        sheet.set_column_width(col, 4.0)?;
+    }
+    sheet.set_row_height(header_row, 30.0)?;
+
+    let totals_col = FIRST_DAY_COL + days.len() as u16;
+    for (idx, code) in CODES.iter().enumerate() {
+        sheet.write_string_with_format(header_row, totals_col + idx as u16, *code, &day_header_format)?;
+        sheet.set_column_width(totals_col + idx as u16, 5.0)?;
+    }
+    let sum_col = totals_col + CODES.len() as u16;
+    sheet.write_string_with_format(header_row, sum_col, "Razem", &day_header_format)?;
+    sheet.set_column_width(sum_col, 7.0)?;
+
+    for (idx, row_data) in rows.iter().enumerate() {
+        let row = header_row + 1 + idx as u32;
+        sheet.write_string(row, 0, privacy.name(&row_data.employee.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&row_data.employee.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&row_data.employee.pesel))?;
+
+        for (day_idx, day) in days.iter().enumerate() {
+            let col = FIRST_DAY_COL + day_idx as u16;
+            let free_day = !calendar::is_working_day(*day);
+            match (row_data.codes[day_idx], free_day) {
+                (Some(code), true) => sheet.write_string_with_format(row, col, code, &free_day_code_format)?,
+                (Some(code), false) => sheet.write_string_with_format(row, col, code, &code_format)?,
+                (None, true) => sheet.write_blank(row, col, &free_day_format)?,
+                (None, false) => sheet.write_blank(row, col, &cell_format)?,
+            };
+        }
+
+        // Sumy jako formuły, żeby przeliczały się po ręcznym uzupełnieniu listy
+        let first_cell = row_col_to_cell(row, FIRST_DAY_COL);
+        let last_cell = row_col_to_cell(row, totals_col - 1);
+        for (code_idx, code) in CODES.iter().enumerate() {
+            let count = row_data.codes.iter().filter(|c| **c == Some(*code)).count();
+            let formula = Formula::new(format!("=COUNTIF({}:{},\"{}\")", first_cell, last_cell, code))
+                .set_result(count.to_string());
+            sheet.write_formula(row, totals_col + code_idx as u16, formula)?;
+        }
+        let total = row_data.codes.iter().filter(|c| c.is_some()).count();
+        let formula = Formula::new(format!(
+            "=SUM({}:{})",
+            row_col_to_cell(row, totals_col),
+            row_col_to_cell(row, sum_col - 1)
+        ))
+        .set_result(total.to_string());
+        sheet.write_formula(row, sum_col, formula)?;
+    }
+
+    let legend_row = header_row + rows.len() as u32 + 2;
+    sheet.write_string(
+        legend_row,
+        0,
+        format!("{} - choroba, {} - opieka, {} - pobyt w szpitalu", CODE_ILLNESS, CODE_CARE, CODE_HOSPITAL),
+    )?;
+    sheet.write_blank(legend_row + 1, 0, &free_day_format)?;
+    sheet.write_string(legend_row + 1, 1, "dzień wolny (weekend lub święto)")?;
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "styczeń",
+    "luty",
+    "marzec",
+    "kwiecień",
+    "maj",
+    "czerwiec",
+    "lipiec",
+    "sierpień",
+    "wrzesień",
+    "październik",
+    "listopad",
+    "grudzień",
+];
+
+pub const WEEKDAY_NAMES: [&str; 7] = ["pn", "wt", "śr", "cz", "pt", "so", "nd"];
+
+// Niedziela Wielkanocna wg algorytmu Meeusa/Jonesa/Butchera
+fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+// Dni ustawowo wolne od pracy w Polsce (ustawa o dniach wolnych od pracy)
+pub fn is_public_holiday(date: NaiveDate) -> bool {
+    let fixed = matches!(
+        (date.month(), date.day()),
+        (1, 1) | (1, 6) | (5, 1) | (5, 3) | (8, 15) | (11, 1) | (11, 11) | (12, 25) | (12, 26)
+    );
+    // Wigilia jest dniem wolnym od 2025 roku
+    let christmas_eve = date.year() >= 2025 && date.month() == 12 && date.day() == 24;
+
+    let easter = easter_sunday(date.year());
+    let movable = [
+        easter,
+        easter + Duration::days(1),  // Poniedziałek Wielkanocny
+        easter + Duration::days(49), // Zielone Świątki
+        easter + Duration::days(60), // Boże Ciało
+    ];
+
+    fixed || christmas_eve || movable.contains(&date)
+}
+
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+pub fn is_working_day(date: NaiveDate) -> bool {
+    !is_weekend(date) && !is_public_holiday(date)
+}
+
+pub fn month_days(year: i32, month: u32) -> Vec<NaiveDate> {
+    let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+        return Vec::new();
+    };
+    first
+        .iter_days()
+        .take_while(|date| date.month() == month)
+        .collect()
+}
+
+pub fn working_days(start: NaiveDate, end: NaiveDate) -> i64 {
+    if start > end {
+        return 0;
+    }
+    start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .filter(|date| is_working_day(*date))
+        .count() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn easter_sunday_in_known_years() {
+        assert_eq!(easter_sunday(2000), date(2000, 4, 23));
+        assert_eq!(easter_sunday(2019), date(2019, 4, 21));
+        assert_eq!(easter_sunday(2024), date(2024, 3, 31));
+        assert_eq!(easter_sunday(2025), date(2025, 4, 20));
+        assert_eq!(easter_sunday(2026), date(2026, 4, 5));
+    }
+
+    #[test]
+    fn movable_holidays() {
+        // Poniedziałek Wielkanocny, Zielone Świątki, Boże Ciało
+        for holiday in [date(2025, 4, 21), date(2025, 6, 8), date(2025, 6, 19)] {
+            assert!(is_public_holiday(holiday), "{}", holiday);
+        }
+        for holiday in [date(2026, 4, 6), date(2026, 5, 24), date(2026, 6, 4)] {
+            assert!(is_public_holiday(holiday), "{}", holiday);
+        }
+        // Dzień po Bożym Ciele i Wielki Piątek są dniami roboczymi
+        assert!(!is_public_holiday(date(2025, 6, 20)));
+        assert!(!is_public_holiday(date(2026, 4, 3)));
+    }
+
+    #[test]
+    fn christmas_eve_is_a_holiday_from_2025() {
+        assert!(!is_public_holiday(date(2024, 12, 24)));
+        assert!(is_public_holiday(date(2025, 12, 24)));
+        assert!(is_public_holiday(date(2026, 12, 24)));
+    }
+
+    #[test]
+    fn working_days_in_december() {
+        // 8 dni weekendu, Wigilia i oba dni Bożego Narodzenia w dni robocze
+        assert_eq!(working_days(date(2025, 12, 1), date(2025, 12, 31)), 20);
+        // Przed 2025 rokiem Wigilia była dniem roboczym
+        assert_eq!(working_days(date(2024, 12, 23), date(2024, 12, 27)), 3);
+        assert_eq!(working_days(date(2025, 12, 31), date(2025, 12, 1)), 0);
+    }
+}
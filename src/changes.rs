@@ -0,0 +1,397 @@
+use anyhow::{anyhow, bail, Context, Result};
+use calamine::{open_workbook, DataType, Reader, Xlsx};
+use chrono::NaiveDate;
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::excel_merger::{date_to_excel, excel_to_date, EmployeeData, ExcelMerger};
+use crate::layout::{self, Field, ReportLayout};
+use crate::privacy::Privacy;
+
+pub const SHEET_NAME: &str = "Zmiany";
+
+// Dane, z którymi porównywany jest bieżący wynik
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangesSource {
+    // Ostatni import zapisany w bazie historii z innym plikiem L4 lub za inny okres
+    PreviousRun,
+    // Import o podanym numerze z bazy historii
+    Import(i64),
+    // Arkusz z danymi wcześniejszego raportu XLSX
+    File(PathBuf),
+}
+
+impl ChangesSource {
+    pub fn describe(&self) -> String {
+        match self {
+            ChangesSource::PreviousRun => "poprzednie uruchomienie".to_string(),
+            ChangesSource::Import(id) => format!("import nr {}", id),
+            ChangesSource::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    Nowe,
+    Anulowane,
+    ZmianaStatusu,
+    ZmianaDat,
+    Usuniete,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::Nowe => "Nowe zaświadczenie",
+            ChangeKind::Anulowane => "Anulowane",
+            ChangeKind::ZmianaStatusu => "Zmiana statusu",
+            ChangeKind::ZmianaDat => "Zmiana dat",
+            ChangeKind::Usuniete => "Brak w bieżących danych",
+        }
+    }
+}
+
+pub struct Change<'a> {
+    pub rodzaj: ChangeKind,
+    // Zaświadczenie w bieżących danych (None - zaświadczenie zniknęło)
+    pub biezace: Option<&'a EmployeeData>,
+    // Zaświadczenie w poprzednich danych (None - nowe zaświadczenie)
+    pub poprzednie: Option<&'a EmployeeData>,
+}
+
+impl Change<'_> {
+    fn employee(&self) -> &EmployeeData {
+        self.biezace.or(self.poprzednie).expect("zmiana dotyczy co najmniej jednego zaświadczenia")
+    }
+}
+
+// Zaświadczenie jest identyfikowane numerem i numerem PESEL; zaświadczenia bez numeru są pomijane.
+// Gdy poprzednie dane nie mają numerów (raport w układzie bez tej kolumny) - datą początku i numerem PESEL.
+fn key(employee: &EmployeeData, by_number: bool) -> Option<(String, String)> {
+    let id = if by_number {
+        employee.nr_zaswiadczenia.trim().to_string()
+    } else {
+        employee.data_od.and_then(excel_to_date)?.format("%Y-%m-%d").to_string()
+    };
+    (!id.is_empty()).then(|| (id, employee.pesel.trim().to_string()))
+}
+
+fn dates(employee: &EmployeeData) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    (employee.data_od.and_then(excel_to_date), employee.data_do.and_then(excel_to_date))
+}
+
+// Zaświadczenie ze zmianą statusu i dat pojawia się w obu kategoriach
+pub fn compare<'a>(previous: &'a [EmployeeData], current: &'a [EmployeeData]) -> Vec<Change<'a>> {
+    let by_number = previous.is_empty() || previous.iter().any(|employee| !employee.nr_zaswiadczenia.trim().is_empty());
+    let previous_by_key: HashMap<(String, String), &EmployeeData> =
+        previous.iter().filter_map(|employee| Some((key(employee, by_number)?, employee))).collect();
+
+    let mut changes = Vec::new();
+    let mut seen = HashSet::new();
+    for employee in current {
+        let Some(key) = key(employee, by_number) else {
+            continue;
+        };
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let Some(before) = previous_by_key.get(&key).copied() else {
+            changes.push(Change { rodzaj: ChangeKind::Nowe, biezace: Some(employee), poprzednie: None });
+            continue;
+        };
+        if before.status.trim() != employee.status.trim() {
+            let rodzaj = if employee.is_cancelled() && !before.is_cancelled() {
+                ChangeKind::Anulowane
+            } else {
+                ChangeKind::ZmianaStatusu
+            };
+            changes.push(Change { rodzaj, biezace: Some(employee), poprzednie: Some(before) });
+        }
+        if dates(before) != dates(employee) {
+            changes.push(Change { rodzaj: ChangeKind::ZmianaDat, biezace: Some(employee), poprzednie: Some(before) });
+        }
+    }
+    for (key, before) in &previous_by_key {
+        if !seen.contains(key) {
+            changes.push(Change { rodzaj: ChangeKind::Usuniete, biezace: None, poprzednie: Some(before) });
+        }
+    }
+
+    changes.sort_by(|a, b| {
+        a.rodzaj
+            .cmp(&b.rodzaj)
+            .then_with(|| a.employee().nazwisko.cmp(&b.employee().nazwisko))
+            .then_with(|| a.employee().pesel.cmp(&b.employee().pesel))
+            .then_with(|| a.employee().nr_zaswiadczenia.cmp(&b.employee().nr_zaswiadczenia))
+    });
+    changes
+}
+
+pub fn summary(changes: &[Change]) -> BTreeMap<ChangeKind, usize> {
+    let mut counts = BTreeMap::new();
+    for change in changes {
+        *counts.entry(change.rodzaj).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Kolumna poprzedniego raportu z danym polem - według nagłówka domyślnego albo nagłówka z któregokolwiek układu
+fn find_column(headers: &[String], field: &Field, layouts: &[ReportLayout]) -> Option<usize> {
+    let mut names: Vec<String> = vec![field.label().to_lowercase()];
+    for layout in layouts {
+        if let Some(position) = layout.position(field) {
+            names.push(layout.kolumny[position].naglowek.trim().to_lowercase());
+        }
+    }
+    headers.iter().position(|header| names.contains(header))
+}
+
+fn cell_text(row: &[DataType], col: Option<usize>) -> String {
+    col.and_then(|col| row.get(col)).map(|cell| cell.to_string().trim().to_string()).unwrap_or_default()
+}
+
+fn cell_date(row: &[DataType], col: Option<usize>) -> Option<f64> {
+    col.and_then(|col| row.get(col)).and_then(ExcelMerger::cell_to_date)
+}
+
+// Zaświadczenia z arkusza "Dane" wcześniejszego raportu XLSX (albo z ostatniego arkusza w pliku
+// prowadzonym w trybie dopisywania). Raport musi zawierać pełny numer PESEL i datę początku
+// albo numer zaświadczenia.
+pub fn read_report(path: &Path, layout: &ReportLayout) -> Result<Vec<EmployeeData>> {
+    let mut workbook: Xlsx<_> = open_workbook(path)
+        .with_context(|| format!("Nie można otworzyć poprzedniego raportu {}", path.display()))?;
+    let sheet = workbook
+        .sheet_names()
+        .iter()
+        .find(|name| name.as_str() == "Dane")
+        .or_else(|| workbook.sheet_names().last())
+        .cloned()
+        .ok_or_else(|| anyhow!("Poprzedni raport {} nie zawiera arkuszy", path.display()))?;
+    let range = workbook
+        .worksheet_range(&sheet)
+        .ok_or_else(|| anyhow!("Nie można otworzyć arkusza {}", sheet))??;
+
+    let mut rows = range.rows();
+    let headers: Vec<String> = rows
+        .next()
+        .map(|row| row.iter().map(|cell| cell.to_string().trim().to_lowercase()).collect())
+        .unwrap_or_default();
+    let mut layouts = layout::all()?;
+    layouts.push(layout.clone());
+    let column = |field: Field| find_column(&headers, &field, &layouts);
+
+    let columns = [
+        Field::NrZaswiadczenia,
+        Field::Pesel,
+        Field::Nazwisko,
+        Field::Imie,
+        Field::Dzial,
+        Field::DataWystawienia,
+        Field::DataOd,
+        Field::DataDo,
+        Field::Status,
+    ]
+    .map(column);
+    let [nr, pesel, nazwisko, imie, dzial, wystawienia, od, do_, status] = columns;
+    if pesel.is_none() || (nr.is_none() && od.is_none()) {
+        bail!(
+            "Arkusz {} poprzedniego raportu musi zawierać kolumnę PESEL oraz numer zaświadczenia albo datę początku",
+            sheet
+        );
+    }
+
+    let mut certificates = Vec::new();
+    let first_row = range.start().map(|(row, _)| row as usize).unwrap_or(0);
+    for (idx, row) in rows.enumerate() {
+        let pesel = cell_text(row, pesel);
+        if pesel.is_empty() {
+            continue;
+        }
+        if pesel.contains('*') || pesel.starts_with("P-") {
+            bail!("Poprzedni raport zawiera maskowane numery PESEL - porównanie wymaga raportu z pełnymi danymi");
+        }
+        certificates.push(EmployeeData {
+            nazwisko: cell_text(row, nazwisko),
+            imie: cell_text(row, imie),
+            pesel,
+            data_od: cell_date(row, od),
+            data_do: cell_date(row, do_),
+            na_opieke: String::new(),
+            pobyt_w_szpitalu: String::new(),
+            status: cell_text(row, status),
+            source: "poprzedni".to_string(),
+            dzial: cell_text(row, dzial),
+            nr_zaswiadczenia: cell_text(row, nr),
+            data_wystawienia: cell_date(row, wystawienia),
+            data_zatrudnienia: None,
+            wskazania: String::new(),
+            wiersz: first_row + idx + 2,
+            dodatkowe: Vec::new(),
+        });
+    }
+    Ok(certificates)
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    changes: &[Change],
+    compared_with: &str,
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(SHEET_NAME)?;
+
+    let headers = [
+        "Zmiana",
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Seria i nr zaśw.",
+        "Poprzedni status",
+        "Status",
+        "Poprzednio od",
+        "Poprzednio do",
+        "Data od",
+        "Data do",
+    ];
+    let widths = [22.0, 20.0, 15.0, 14.0, 16.0, 16.0, 16.0, 13.0, 13.0, 12.0, 12.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, change) in changes.iter().enumerate() {
+        let row = idx as u32 + 1;
+        let employee = change.employee();
+        sheet.write_string(row, 0, change.rodzaj.label())?;
+        sheet.write_string(row, 1, privacy.name(&employee.nazwisko))?;
+        sheet.write_string(row, 2, privacy.name(&employee.imie))?;
+        sheet.write_string(row, 3, privacy.pesel(&employee.pesel))?;
+        sheet.write_string(row, 4, &employee.nr_zaswiadczenia)?;
+        for (col, certificate) in [(5, change.poprzednie), (6, change.biezace)] {
+            if let Some(certificate) = certificate {
+                sheet.write_string(row, col, &certificate.status)?;
+            }
+        }
+        for (col, certificate) in [(7, change.poprzednie), (9, change.biezace)] {
+            let (start, end) = certificate.map(dates).unwrap_or_default();
+            for (offset, date) in [start, end].into_iter().enumerate() {
+                if let Some(date) = date {
+                    sheet.write_number_with_format(row, col + offset as u16, date_to_excel(date), date_format)?;
+                }
+            }
+        }
+    }
+
+    sheet.write_string(changes.len() as u32 + 2, 0, format!("Porównanie z: {}", compared_with))?;
+    if !changes.is_empty() {
+        sheet.autofilter(0, 0, changes.len() as u32, headers.len() as u16 - 1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(nr: &str, pesel: &str, start: (i32, u32, u32), end: (i32, u32, u32), status: &str) -> EmployeeData {
+        let date = |(year, month, day): (i32, u32, u32)| Some(date_to_excel(NaiveDate::from_ymd_opt(year, month, day).unwrap()));
+        EmployeeData {
+            nazwisko: format!("Nazwisko {}", pesel),
+            pesel: pesel.to_string(),
+            nr_zaswiadczenia: nr.to_string(),
+            data_od: date(start),
+            data_do: date(end),
+            status: status.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn kinds(changes: &[Change]) -> Vec<(ChangeKind, String)> {
+        changes.iter().map(|change| (change.rodzaj, change.employee().pesel.clone())).collect()
+    }
+
+    #[test]
+    fn compare_by_certificate_number() {
+        let previous = vec![
+            certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione"),
+            certificate("ZUS2", "90020254321", (2026, 10, 5), (2026, 10, 9), "Wystawione"),
+            certificate("ZUS3", "75030398765", (2026, 10, 1), (2026, 10, 3), "Wystawione"),
+            certificate("ZUS4", "80040411111", (2026, 10, 2), (2026, 10, 4), "Wystawione"),
+        ];
+        let current = vec![
+            // Bez zmian
+            certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione"),
+            certificate("ZUS2", "90020254321", (2026, 10, 5), (2026, 10, 9), "Anulowane"),
+            certificate("ZUS3", "75030398765", (2026, 10, 1), (2026, 10, 7), "Wystawione"),
+            certificate("ZUS5", "95050522222", (2026, 10, 20), (2026, 10, 25), "Wystawione"),
+        ];
+        let changes = compare(&previous, &current);
+        assert_eq!(
+            kinds(&changes),
+            vec![
+                (ChangeKind::Nowe, "95050522222".to_string()),
+                (ChangeKind::Anulowane, "90020254321".to_string()),
+                (ChangeKind::ZmianaDat, "75030398765".to_string()),
+                (ChangeKind::Usuniete, "80040411111".to_string()),
+            ]
+        );
+        let cancelled = &changes[1];
+        assert_eq!(cancelled.poprzednie.unwrap().status, "Wystawione");
+        assert_eq!(cancelled.biezace.unwrap().status, "Anulowane");
+    }
+
+    #[test]
+    fn status_and_date_change_is_reported_twice() {
+        let previous = vec![certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione")];
+        let current = vec![certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 12), "Skorygowane")];
+        let changes = compare(&previous, &current);
+        let found: Vec<ChangeKind> = changes.iter().map(|change| change.rodzaj).collect();
+        assert_eq!(found, vec![ChangeKind::ZmianaStatusu, ChangeKind::ZmianaDat]);
+    }
+
+    #[test]
+    fn same_number_of_another_person_is_a_different_certificate() {
+        let previous = vec![certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione")];
+        let current = vec![certificate("ZUS1", "90020254321", (2026, 10, 1), (2026, 10, 10), "Wystawione")];
+        let found: Vec<ChangeKind> = compare(&previous, &current).iter().map(|change| change.rodzaj).collect();
+        assert_eq!(found, vec![ChangeKind::Nowe, ChangeKind::Usuniete]);
+    }
+
+    #[test]
+    fn compare_without_certificate_numbers_uses_pesel_and_start_date() {
+        let previous = vec![
+            certificate("", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione"),
+            certificate("", "90020254321", (2026, 10, 5), (2026, 10, 9), "Wystawione"),
+        ];
+        let current = vec![
+            // Inna data końca przy tej samej dacie początku - to samo zaświadczenie
+            certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 14), "Wystawione"),
+            // Inna data początku - w porównaniu bez numeru to nowe zaświadczenie
+            certificate("ZUS2", "90020254321", (2026, 10, 6), (2026, 10, 9), "Wystawione"),
+        ];
+        let changes = compare(&previous, &current);
+        assert_eq!(
+            kinds(&changes),
+            vec![
+                (ChangeKind::Nowe, "90020254321".to_string()),
+                (ChangeKind::ZmianaDat, "85010112345".to_string()),
+                (ChangeKind::Usuniete, "90020254321".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn certificates_without_number_are_skipped() {
+        let previous = vec![certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione")];
+        let current = vec![
+            certificate("ZUS1", "85010112345", (2026, 10, 1), (2026, 10, 10), "Wystawione"),
+            certificate("", "90020254321", (2026, 10, 5), (2026, 10, 9), "Wystawione"),
+        ];
+        assert!(compare(&previous, &current).is_empty());
+    }
+}
@@ -0,0 +1,573 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::changes::ChangesSource;
+use crate::control::ControlCriteria;
+use crate::encryption;
+use crate::excel_merger::{ExcelMerger, MergeOptions};
+use crate::history;
+use crate::ics::{self, IcsOptions};
+use crate::layout::{self, ReportLayout};
+use crate::manifest;
+use crate::output::{CsvEncoding, CsvOptions, OutputFormat};
+use crate::payroll::{self, PayrollOptions};
+use crate::privacy::PrivacyMode;
+use crate::split::{self, SplitOptions};
+
+const USAGE: &str = "Użycie:
+  l4-filter raport --pracownicy <plik> --l4 <plik> [opcje]
+  l4-filter nieobecni --pracownicy <plik> --l4 <plik> [--data RRRR-MM-DD] [opcje]
+  l4-filter uklady                 lista układów raportu i położenie pliku z układami
+  l4-filter profile [--zapisz]     lista profili płacowych; --zapisz zapisuje je do pliku do edycji
+  l4-filter historia [--rok RRRR]  historia importów; z --rok dni zwolnień każdej osoby w roku ze wszystkich importów
+  l4-filter weryfikuj --manifest <plik> | --wynik <plik> [--pracownicy <plik>] [--l4 <plik>]
+                                   sprawdź sumy SHA-256 plików raportu z manifestem zapisanym przy raporcie;
+                                   zaszyfrowany manifest (.zip) jest otwierany hasłem z L4_HASLO;
+                                   --pracownicy i --l4 wskazują pliki wejściowe przeniesione w inne miejsce";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Arity {
+    // Przełącznik bez wartości, np. --szyfruj
+    Flag,
+    // Opcja z wymaganą wartością, np. --wynik <plik>
+    Value(&'static str),
+    // Opcja, której wartość można pominąć, np. --zmiany [plik]
+    OptionalValue(&'static str),
+}
+
+// Opcja wiersza poleceń: nazwa bez "--", liczba wartości i opis do pomocy (kolejne wiersze po \n)
+struct CliOption {
+    name: &'static str,
+    arity: Arity,
+    help: &'static str,
+}
+
+const fn flag(name: &'static str, help: &'static str) -> CliOption {
+    CliOption { name, arity: Arity::Flag, help }
+}
+
+const fn value(name: &'static str, placeholder: &'static str, help: &'static str) -> CliOption {
+    CliOption { name, arity: Arity::Value(placeholder), help }
+}
+
+const fn optional(name: &'static str, placeholder: &'static str, help: &'static str) -> CliOption {
+    CliOption { name, arity: Arity::OptionalValue(placeholder), help }
+}
+
+const INPUT_OPTIONS: [CliOption; 4] = [
+    value("pracownicy", "<plik>", "plik z listą pracowników (XLSX lub XLS)"),
+    value("l4", "<plik>", "plik z danymi L4 (XLSX lub XLS)"),
+    value("arkusz-pracownicy", "<nazwa>", "arkusz z listą pracowników (domyślnie pierwszy)"),
+    value("arkusz-l4", "<nazwa>", "arkusz z danymi L4 (domyślnie pierwszy)"),
+];
+
+const REPORT_OPTIONS: [CliOption; 34] = [
+    value("wynik", "<plik>", "plik wynikowy (domyślnie L4_DD-MM-RRRR.xlsx)"),
+    value("format", "<format>", "xlsx, csv, json, ndjson, pdf, html lub ics (domyślnie według rozszerzenia pliku wynikowego)"),
+    value("firma", "<nazwa>", "nazwa firmy w nagłówku raportu PDF i HTML"),
+    value("tytul", "<szablon>", "tytuł wydarzeń ICS, np. \"{imie} {nazwisko} – nieobecność\""),
+    flag("dane-medyczne", "w opisie wydarzeń ICS podaj opiekę, pobyt w szpitalu i wskazania"),
+    value("dane-osobowe", "<tryb>", "pelny, maskowany, pseudonim lub zanonimizowany (domyślnie pelny)"),
+    flag(
+        "szyfruj",
+        "zapisz pliki wynikowe jako archiwa ZIP szyfrowane AES-256 hasłem\nze zmiennej środowiskowej L4_HASLO",
+    ),
+    value("separator", "<znak>", "separator pól CSV, np. \";\", \",\" lub tab (domyślnie ;)"),
+    value("kodowanie", "<kodowanie>", "kodowanie CSV: utf8, utf8-bom lub cp1250 (domyślnie utf8-bom)"),
+    value("szablon", "<plik>", "wpisz dane do kopii szablonu ze znacznikiem {{dane}}"),
+    flag("dopisz", "dopisz arkusz RRRR-MM z danymi do istniejącego pliku wynikowego"),
+    flag("zastap", "w trybie --dopisz zastąp arkusz o tej samej nazwie"),
+    value("uklad", "<nazwa>", "układ kolumn arkusza z danymi (domyślnie Pełny)"),
+    value("kolumny", "<lista>", "dodatkowe kolumny z listy pracowników, np. \"Dział,Stanowisko\""),
+    value("podziel", "<kolumna>", "dodatkowo osobny plik dla każdej wartości kolumny z listy pracowników"),
+    value("szablon-nazwy", "<szablon>", "nazwa plików podziału, {wartosc} i {data} (domyślnie L4_{wartosc}.xlsx)"),
+    value("place", "<plik>", "dodatkowo plik z nieobecnościami dla programu płacowego"),
+    value("profil-placowy", "<nazwa>", "profil pliku płacowego (domyślnie CSV)"),
+    value("powroty", "<dni>", "dodaj arkusz powrotów do pracy w ciągu podanej liczby dni"),
+    value("miesiac", "<RRRR-MM>", "dodaj arkusz z listą obecności za podany miesiąc"),
+    value("statystyki-od", "<RRRR-MM-DD>", "początek okresu statystyk absencji (arkusz Statystyki)"),
+    value("statystyki-do", "<RRRR-MM-DD>", "koniec okresu statystyk absencji"),
+    value("statystyki-json", "<plik>", "zapisz statystyki także do pliku JSON"),
+    flag("bez-wykresow", "nie dodawaj wykresów do arkusza Podsumowanie"),
+    flag("bez-anomalii", "pomiń sprawdzanie danych pod kątem anomalii"),
+    flag("zapisz-historie", "zapisz zaświadczenia w lokalnej bazie historii (niedostępne z --szyfruj)"),
+    optional(
+        "zmiany",
+        "[plik]",
+        "dodaj arkusz Zmiany: porównanie z poprzednim uruchomieniem zapisanym\n\
+         w historii (z innym plikiem L4 lub za inny okres) albo z podanym\n\
+         wcześniejszym raportem XLSX",
+    ),
+    value(
+        "zmiany-import",
+        "<nr>",
+        "dodaj arkusz Zmiany: porównanie z importem o podanym numerze\n(l4-filter historia)",
+    ),
+    value(
+        "epizody",
+        "<przerwa>",
+        "dodaj arkusz epizodów nieobecności (przerwa w dniach, 0 - tylko ciągłe);\n\
+         w pliku ICS jedno wydarzenie na epizod",
+    ),
+    flag("kontrole", "dodaj arkusz z kandydatami do kontroli zwolnień"),
+    value("kontrole-min-dni", "<n>", "minimalna długość bieżącego zwolnienia (domyślnie 14)"),
+    value("kontrole-min-zasw", "<n>", "minimalna liczba zaświadczeń w okresie (domyślnie 3)"),
+    value("kontrole-okres", "<dni>", "okres wstecz dla liczby zaświadczeń (domyślnie 90)"),
+    flag("kontrole-tylko-lezacy", "tylko wskazanie \"1 - chory powinien leżeć\""),
+];
+
+const SNAPSHOT_OPTIONS: [CliOption; 1] = [value("data", "RRRR-MM-DD", "dzień, na który wypisać nieobecnych (domyślnie dziś)")];
+
+const PROFILE_OPTIONS: [CliOption; 1] = [flag("zapisz", "zapisz profile do pliku do edycji")];
+
+const HISTORY_OPTIONS: [CliOption; 1] = [value("rok", "RRRR", "dni zwolnień każdej osoby w roku")];
+
+const VERIFY_OPTIONS: [CliOption; 4] = [
+    value("manifest", "<plik>", "plik manifestu"),
+    value("wynik", "<plik>", "plik wynikowy raportu, obok którego zapisano manifest"),
+    value("pracownicy", "<plik>", "lista pracowników przeniesiona w inne miejsce"),
+    value("l4", "<plik>", "plik L4 przeniesiony w inne miejsce"),
+];
+
+// Opcje dozwolone w poleceniu; None - nieznane polecenie
+fn command_options(command: &str) -> Option<&'static [&'static [CliOption]]> {
+    Some(match command {
+        "raport" => &[&INPUT_OPTIONS, &REPORT_OPTIONS],
+        "nieobecni" => &[&INPUT_OPTIONS, &SNAPSHOT_OPTIONS],
+        "profile" => &[&PROFILE_OPTIONS],
+        "historia" => &[&HISTORY_OPTIONS],
+        "weryfikuj" => &[&VERIFY_OPTIONS],
+        "uklady" | "pomoc" | "--help" | "-h" => &[],
+        _ => return None,
+    })
+}
+
+fn usage() -> String {
+    let mut text = USAGE.to_string();
+    for (title, options) in [("Opcje wspólne", &INPUT_OPTIONS[..]), ("Opcje polecenia raport", &REPORT_OPTIONS[..])] {
+        text.push_str(&format!("\n\n{}:", title));
+        for option in options {
+            let name = match option.arity {
+                Arity::Flag => format!("--{}", option.name),
+                Arity::Value(placeholder) | Arity::OptionalValue(placeholder) => {
+                    format!("--{} {}", option.name, placeholder)
+                }
+            };
+            let mut lines = option.help.lines();
+            text.push_str(&format!("\n  {:<30}{}", name, lines.next().unwrap_or_default()));
+            for line in lines {
+                text.push_str(&format!("\n{:32}{}", "", line));
+            }
+        }
+    }
+    text
+}
+
+struct CliArgs {
+    command: String,
+    values: HashMap<String, String>,
+}
+
+impl CliArgs {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut iter = args.iter();
+        let command = iter.next().cloned().unwrap_or_default();
+        let known = command_options(&command).ok_or_else(|| anyhow!("Nieznane polecenie: {}\n\n{}", command, usage()))?;
+        let mut values = HashMap::new();
+
+        while let Some(arg) = iter.next() {
+            let key = arg
+                .strip_prefix("--")
+                .ok_or_else(|| anyhow!("Nieoczekiwany argument: {}", arg))?;
+            let option = known
+                .iter()
+                .flat_map(|options| options.iter())
+                .find(|option| option.name == key)
+                .ok_or_else(|| anyhow!("Nieznana opcja polecenia {}: {}", command, arg))?;
+            let next_value = match iter.as_slice().first() {
+                Some(next) if !next.starts_with("--") => iter.next().cloned(),
+                _ => None,
+            };
+            let value = match option.arity {
+                Arity::Flag => match next_value {
+                    Some(next) => bail!("Opcja --{} nie przyjmuje wartości: {}", key, next),
+                    None => String::new(),
+                },
+                Arity::Value(_) => next_value
+                    .filter(|value| !value.trim().is_empty())
+                    .ok_or_else(|| anyhow!("Opcja --{} wymaga wartości", key))?,
+                Arity::OptionalValue(_) => next_value.unwrap_or_default(),
+            };
+            if values.insert(key.to_string(), value).is_some() {
+                bail!("Opcja --{} została podana więcej niż raz", key);
+            }
+        }
+
+        Ok(Self { command, values })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|s| s.as_str())
+    }
+
+    fn require(&self, key: &str) -> Result<&str> {
+        self.get(key)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| anyhow!("Brak wymaganej opcji --{}", key))
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    fn number<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .parse::<T>()
+                    .map_err(|_| anyhow!("Niepoprawna liczba w opcji --{}: {}", key, value))
+            })
+            .transpose()
+    }
+
+    fn date(&self, key: &str) -> Result<Option<NaiveDate>> {
+        self.get(key)
+            .map(|value| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map_err(|_| anyhow!("Niepoprawna data w opcji --{}: {} (oczekiwano RRRR-MM-DD)", key, value))
+            })
+            .transpose()
+    }
+}
+
+// Tryb wiersza poleceń - uruchamiany, gdy program dostanie jakiekolwiek argumenty
+pub fn run(args: &[String]) -> i32 {
+    attach_console();
+
+    let mut merger = ExcelMerger::new();
+    let result = CliArgs::parse(args).and_then(|args| execute(&mut merger, &args));
+
+    for message in merger.messages.drain(..) {
+        println!("{}", message.trim_start_matches('\n'));
+    }
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Błąd: {}", e);
+            1
+        }
+    }
+}
+
+fn execute(merger: &mut ExcelMerger, args: &CliArgs) -> Result<()> {
+    match args.command.as_str() {
+        "raport" => run_report(merger, args),
+        "nieobecni" => run_snapshot(merger, args),
+        "uklady" => list_layouts(),
+        "profile" => list_payroll_profiles(args),
+        "weryfikuj" => run_verify(args),
+        "historia" => show_history(args),
+        "pomoc" | "--help" | "-h" => {
+            println!("{}", usage());
+            Ok(())
+        }
+        other => bail!("Nieznane polecenie: {}\n\n{}", other, usage()),
+    }
+}
+
+struct InputFiles {
+    first_file: PathBuf,
+    second_file: PathBuf,
+    first_sheet: String,
+    second_sheet: String,
+}
+
+fn input_files(merger: &mut ExcelMerger, args: &CliArgs) -> Result<InputFiles> {
+    let first_file = PathBuf::from(args.require("pracownicy")?);
+    let second_file = PathBuf::from(args.require("l4")?);
+    let first_sheet = sheet_or_first(merger, &first_file, args.get("arkusz-pracownicy"))?;
+    let second_sheet = sheet_or_first(merger, &second_file, args.get("arkusz-l4"))?;
+
+    Ok(InputFiles { first_file, second_file, first_sheet, second_sheet })
+}
+
+fn sheet_or_first(merger: &mut ExcelMerger, path: &PathBuf, sheet: Option<&str>) -> Result<String> {
+    if let Some(sheet) = sheet {
+        return Ok(sheet.to_string());
+    }
+    merger
+        .get_sheet_names(path)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Nie znaleziono arkuszy w pliku {}", path.display()))
+}
+
+fn run_report(merger: &mut ExcelMerger, args: &CliArgs) -> Result<()> {
+    let input = input_files(merger, args)?;
+    let output_file = args
+        .get("wynik")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("L4_{}.xlsx", chrono::Local::now().format("%d-%m-%Y"))));
+
+    let return_window_days = args.number::<u32>("powroty")?;
+    let attendance_month = args.get("miesiac").map(parse_month).transpose()?;
+    let statistics_period = match (args.date("statystyki-od")?, args.date("statystyki-do")?) {
+        (Some(start), Some(end)) if start > end => {
+            bail!("Początek okresu statystyk (--statystyki-od) jest po jego końcu (--statystyki-do)")
+        }
+        (Some(start), Some(end)) => Some((start, end)),
+        (None, None) => None,
+        _ => bail!("Opcje --statystyki-od i --statystyki-do trzeba podać razem"),
+    };
+    let statistics_json = args.get("statystyki-json").map(PathBuf::from);
+    if statistics_json.is_some() && statistics_period.is_none() {
+        bail!("Opcja --statystyki-json wymaga podania okresu statystyk");
+    }
+
+    let options = MergeOptions {
+        return_window_days,
+        attendance_month,
+        statistics_period,
+        statistics_json,
+        check_anomalies: !args.flag("bez-anomalii"),
+        control_criteria: control_criteria(args)?,
+        episode_gap_days: args.number("epizody")?,
+        extra_columns: args.get("kolumny").map(split_list).unwrap_or_default(),
+        split: args.get("podziel").map(|column| SplitOptions {
+            column: column.to_string(),
+            file_name_template: args.get("szablon-nazwy").unwrap_or(split::DEFAULT_TEMPLATE).to_string(),
+        }),
+        charts: !args.flag("bez-wykresow"),
+        layout: match args.get("uklad") {
+            Some(name) => layout::find(name)?,
+            None => ReportLayout::default(),
+        },
+        template: args.get("szablon").map(PathBuf::from),
+        append: args.flag("dopisz"),
+        replace_sheet: args.flag("zastap"),
+        format: args.get("format").map(OutputFormat::parse).transpose()?,
+        csv: CsvOptions {
+            delimiter: args.get("separator").map(parse_delimiter).transpose()?.unwrap_or(';'),
+            encoding: args.get("kodowanie").map(CsvEncoding::parse).transpose()?.unwrap_or(CsvEncoding::Utf8Bom),
+        },
+        company: args.get("firma").unwrap_or_default().to_string(),
+        ics: IcsOptions {
+            summary: args.get("tytul").unwrap_or(ics::DEFAULT_SUMMARY).to_string(),
+            details: args.flag("dane-medyczne"),
+        },
+        payroll: match args.get("place").filter(|file| !file.is_empty()) {
+            Some(file) => Some(PayrollOptions {
+                profile: payroll::find(args.get("profil-placowy").unwrap_or(payroll::DEFAULT_PROFILE))?,
+                file: PathBuf::from(file),
+            }),
+            None if args.flag("profil-placowy") => bail!("Opcja --profil-placowy wymaga podania pliku --place"),
+            None => None,
+        },
+        privacy: args.get("dane-osobowe").map(PrivacyMode::parse).transpose()?.unwrap_or_default(),
+        password: args.flag("szyfruj").then(encryption::password_from_env).transpose()?,
+        history: args.flag("zapisz-historie"),
+        changes: match (args.number::<i64>("zmiany-import")?, args.get("zmiany")) {
+            (Some(_), Some(_)) => bail!("Opcji --zmiany i --zmiany-import nie można łączyć"),
+            (Some(id), None) => Some(ChangesSource::Import(id)),
+            (None, Some("")) => Some(ChangesSource::PreviousRun),
+            (None, Some(file)) => Some(ChangesSource::File(PathBuf::from(file))),
+            (None, None) => None,
+        },
+    };
+
+    merger
+        .merge_files(
+            &input.first_file,
+            &input.second_file,
+            &output_file,
+            &input.first_sheet,
+            &input.second_sheet,
+            &options,
+        )
+        .map_err(|e| anyhow!("{}", e))
+}
+
+fn list_layouts() -> Result<()> {
+    for layout in layout::all()? {
+        println!("{}: {}", layout.nazwa, layout.headers().join(", "));
+    }
+    println!("\nWłasne układy: {}", layout::layouts_file().display());
+    Ok(())
+}
+
+fn list_payroll_profiles(args: &CliArgs) -> Result<()> {
+    for profile in payroll::all()? {
+        let format = match profile.separator {
+            Some(separator) => format!("separator {:?}", separator),
+            None => "stała szerokość".to_string(),
+        };
+        let columns: Vec<&str> = profile.kolumny.iter().map(|column| column.pole.label()).collect();
+        println!("{} ({}, {}): {}", profile.nazwa, format, profile.kodowanie, columns.join(", "));
+        let codes = &profile.kody;
+        println!(
+            "  kody: choroba {} ({}%), szpital {} ({}%), opieka {} ({}%)",
+            codes.choroba.kod, codes.choroba.procent, codes.szpital.kod, codes.szpital.procent, codes.opieka.kod, codes.opieka.procent
+        );
+    }
+    if args.flag("zapisz") {
+        println!("\nZapisano profile do edycji: {}", payroll::save_all()?.display());
+    } else {
+        println!("\nWłasne profile: {}", payroll::profiles_file().display());
+    }
+    Ok(())
+}
+
+fn show_history(args: &CliArgs) -> Result<()> {
+    if let Some(year) = args.number::<i32>("rok")? {
+        let totals = history::year_totals(year)?;
+        println!("Dni zwolnień w roku {} (choroba / opieka, liczba zaświadczeń):", year);
+        for total in &totals {
+            println!(
+                "{} {} ({}{}): {} / {}, zaświadczeń: {}",
+                total.nazwisko,
+                total.imie,
+                total.pesel,
+                if total.dzial.is_empty() { String::new() } else { format!(", {}", total.dzial) },
+                total.dni_choroby,
+                total.dni_opieki,
+                total.zaswiadczenia
+            );
+        }
+        if totals.is_empty() {
+            println!("Brak zaświadczeń w historii za rok {}", year);
+        }
+    } else {
+        let imports = history::imports()?;
+        for entry in &imports {
+            println!(
+                "{}. {} - {} (okres {} - {}): zaświadczeń {}, dopasowanych {}, nowych {}, zmienionych {}",
+                entry.id,
+                entry.czas,
+                entry.plik_l4,
+                entry.okres_od,
+                entry.okres_do,
+                entry.zaswiadczenia,
+                entry.dopasowane,
+                entry.nowe,
+                entry.zmienione
+            );
+        }
+        if imports.is_empty() {
+            println!("Historia importów jest pusta");
+        }
+    }
+    println!("\nBaza historii: {}", history::database_file().display());
+    Ok(())
+}
+
+fn run_verify(args: &CliArgs) -> Result<()> {
+    let manifest_file = match (args.get("manifest"), args.get("wynik")) {
+        (Some(file), _) if !file.is_empty() => PathBuf::from(file),
+        (_, Some(file)) if !file.is_empty() => manifest::find_manifest(Path::new(file)),
+        _ => bail!("Podaj plik manifestu --manifest albo plik wynikowy --wynik"),
+    };
+    let inputs: Vec<(&str, PathBuf)> = ["pracownicy", "l4"]
+        .into_iter()
+        .filter_map(|rola| args.get(rola).filter(|file| !file.is_empty()).map(|file| (rola, PathBuf::from(file))))
+        .collect();
+
+    let password = encryption::is_encrypted(&manifest_file).then(encryption::password_from_env).transpose()?;
+
+    let (manifest, checks) = manifest::verify(&manifest_file, &inputs, password.as_deref())?;
+    println!(
+        "Manifest: {} ({} {}, utworzono {})",
+        manifest_file.display(),
+        manifest.program,
+        manifest.wersja,
+        manifest.utworzono
+    );
+    for check in &checks {
+        println!("{}", check.line());
+    }
+    manifest::ensure_valid(&checks)?;
+    println!("Raport zgodny z manifestem");
+    Ok(())
+}
+
+fn control_criteria(args: &CliArgs) -> Result<Option<ControlCriteria>> {
+    let enabled = ["kontrole", "kontrole-min-dni", "kontrole-min-zasw", "kontrole-okres", "kontrole-tylko-lezacy"]
+        .iter()
+        .any(|key| args.flag(key));
+    if !enabled {
+        return Ok(None);
+    }
+
+    let defaults = ControlCriteria::default();
+    Ok(Some(ControlCriteria {
+        min_days: args.number("kontrole-min-dni")?.unwrap_or(defaults.min_days),
+        min_certificates: args.number("kontrole-min-zasw")?.unwrap_or(defaults.min_certificates),
+        lookback_days: args.number("kontrole-okres")?.unwrap_or(defaults.lookback_days),
+        only_bed_rest: args.flag("kontrole-tylko-lezacy"),
+    }))
+}
+
+fn parse_delimiter(value: &str) -> Result<char> {
+    if value.eq_ignore_ascii_case("tab") || value == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(delimiter), None) => Ok(delimiter),
+        _ => bail!("Separator musi być jednym znakiem albo \"tab\": {}", value),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn parse_month(value: &str) -> Result<(i32, u32)> {
+    let invalid = || anyhow!("Niepoprawny miesiąc: {} (oczekiwano RRRR-MM)", value);
+    let (year, month) = value.split_once('-').ok_or_else(invalid)?;
+    let year = year.parse::<i32>().map_err(|_| invalid())?;
+    let month = month.parse::<u32>().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) {
+        return Err(invalid());
+    }
+    Ok((year, month))
+}
+
+fn run_snapshot(merger: &mut ExcelMerger, args: &CliArgs) -> Result<()> {
+    let input = input_files(merger, args)?;
+    let date = args.date("data")?.unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    let absent = merger.absent_on(
+        &input.first_file,
+        &input.second_file,
+        &input.first_sheet,
+        &input.second_sheet,
+        date,
+    )?;
+
+    for message in merger.messages.drain(..) {
+        println!("{}", message.trim_start_matches('\n'));
+    }
+    for employee in absent {
+        println!("{}", employee);
+    }
+    Ok(())
+}
+
+// Aplikacja jest budowana z `windows_subsystem = "windows"`, więc na Windows trzeba
+// podłączyć się do konsoli procesu nadrzędnego, żeby wypisywany tekst był widoczny.
+#[cfg(windows)]
+fn attach_console() {
+    unsafe {
+        winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, Workbook};
+
+use crate::excel_merger::{date_to_excel, excel_to_date, EmployeeData};
+use crate::privacy::Privacy;
+
+// Zaświadczenie może być wystawione z datą wsteczną najwyżej 3 dni przed dniem badania
+pub const MAX_BACKDATING_DAYS: i64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComplianceFlag {
+    Backdated,
+    IssuedAfterPeriod,
+}
+
+impl ComplianceFlag {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ComplianceFlag::Backdated => "Wystawione wstecz ponad 3 dni",
+            ComplianceFlag::IssuedAfterPeriod => "Wystawione po zakończeniu zwolnienia",
+        }
+    }
+
+    fn describe(&self, start: NaiveDate, end: NaiveDate, wystawione: NaiveDate) -> String {
+        match self {
+            ComplianceFlag::Backdated => format!(
+                "Zwolnienie od {} wystawiono {} ({} dni wstecz)",
+                start.format("%d.%m.%Y"),
+                wystawione.format("%d.%m.%Y"),
+                (wystawione - start).num_days()
+            ),
+            ComplianceFlag::IssuedAfterPeriod => format!(
+                "Zwolnienie do {} wystawiono {}",
+                end.format("%d.%m.%Y"),
+                wystawione.format("%d.%m.%Y")
+            ),
+        }
+    }
+}
+
+pub fn check(employee: &EmployeeData) -> Vec<ComplianceFlag> {
+    let Some(wystawione) = employee.data_wystawienia.and_then(excel_to_date) else {
+        return Vec::new();
+    };
+    let Some((start, end)) = employee.okres() else {
+        return Vec::new();
+    };
+
+    let mut flags = Vec::new();
+    if start < wystawione - Duration::days(MAX_BACKDATING_DAYS) {
+        flags.push(ComplianceFlag::Backdated);
+    }
+    if wystawione > end {
+        flags.push(ComplianceFlag::IssuedAfterPeriod);
+    }
+    flags
+}
+
+// Tekst do kolumny "Weryfikacja" w arkuszu z danymi; anulowane zaświadczenia, tak jak
+// w arkuszu "Do weryfikacji", nie wymagają weryfikacji
+pub fn flags_text(employee: &EmployeeData) -> String {
+    if employee.is_cancelled() {
+        return String::new();
+    }
+    check(employee)
+        .iter()
+        .map(|flag| flag.label())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    data: &[EmployeeData],
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<usize> {
+    let flagged: Vec<(&EmployeeData, ComplianceFlag)> = data
+        .iter()
+        .filter(|employee| !employee.is_cancelled())
+        .flat_map(|employee| check(employee).into_iter().map(move |flag| (employee, flag)))
+        .collect();
+    if flagged.is_empty() {
+        return Ok(0);
+    }
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Do weryfikacji")?;
+
+    let headers = [
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Seria i nr zaśw.",
+        "Data wyst.",
+        "Data od",
+        "Data do",
+        "Powód weryfikacji",
+        "Szczegóły",
+    ];
+    let widths = [20.0, 15.0, 12.0, 16.0, 12.0, 12.0, 12.0, 36.0, 50.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, (employee, flag)) in flagged.iter().enumerate() {
+        // Flagi powstają tylko dla zaświadczeń z kompletem dat
+        let (Some(wystawione), Some((start, end))) =
+            (employee.data_wystawienia.and_then(excel_to_date), employee.okres())
+        else {
+            continue;
+        };
+        let row = idx as u32 + 1;
+        sheet.write_string(row, 0, privacy.name(&employee.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&employee.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&employee.pesel))?;
+        sheet.write_string(row, 3, &employee.nr_zaswiadczenia)?;
+        sheet.write_number_with_format(row, 4, date_to_excel(wystawione), date_format)?;
+        sheet.write_number_with_format(row, 5, date_to_excel(start), date_format)?;
+        sheet.write_number_with_format(row, 6, date_to_excel(end), date_format)?;
+        sheet.write_string(row, 7, flag.label())?;
+        sheet.write_string(row, 8, flag.describe(start, end, wystawione))?;
+    }
+
+    Ok(flagged.len())
+}
@@ -0,0 +1,41 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "l4-filter";
+
+// Katalog ustawień: L4_KONFIGURACJA, jeśli ustawiona, w przeciwnym razie %APPDATA%\l4-filter
+// na Windows albo ~/.config/l4-filter w pozostałych systemach
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("L4_KONFIGURACJA") {
+        return PathBuf::from(dir);
+    }
+    std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR)
+}
+
+// Odczyt pliku JSON z katalogu ustawień; brak pliku oznacza wartość domyślną
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> Result<T> {
+    let path = config_dir().join(file_name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Nie można odczytać pliku {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Niepoprawny format pliku {}", path.display()))
+}
+
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> Result<PathBuf> {
+    let dir = config_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Nie można utworzyć katalogu {}", dir.display()))?;
+    let path = dir.join(file_name);
+    std::fs::write(&path, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("Nie można zapisać pliku {}", path.display()))?;
+    Ok(path)
+}
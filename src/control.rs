@@ -0,0 +1,246 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::HashMap;
+
+use crate::episodes;
+use crate::excel_merger::{date_to_excel, EmployeeData};
+use crate::privacy::Privacy;
+
+// Kod wskazania "1 - chory powinien leżeć"
+pub const BED_REST_CODE: &str = "1";
+
+#[derive(Debug, Clone)]
+pub struct ControlCriteria {
+    // Minimalna długość bieżącego zwolnienia w dniach
+    pub min_days: i64,
+    // Minimalna liczba zaświadczeń w okresie `lookback_days` dni wstecz
+    pub min_certificates: usize,
+    pub lookback_days: i64,
+    // Tylko osoby ze wskazaniem "chory powinien leżeć"
+    pub only_bed_rest: bool,
+}
+
+impl Default for ControlCriteria {
+    fn default() -> Self {
+        Self {
+            min_days: 14,
+            min_certificates: 3,
+            lookback_days: 90,
+            only_bed_rest: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlCandidate<'a> {
+    pub employee: &'a EmployeeData,
+    pub data_od: NaiveDate,
+    pub data_do: NaiveDate,
+    pub dni: i64,
+    pub liczba_zaswiadczen: usize,
+    pub powod: String,
+}
+
+// Kod wskazania lekarskiego to cyfra na początku opisu, np. "1 - chory powinien leżeć"
+pub fn instruction_code(wskazania: &str) -> &str {
+    let trimmed = wskazania.trim();
+    let end = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    &trimmed[..end]
+}
+
+// Długość zwolnienia to długość bieżącego epizodu - kolejnych zaświadczeń bez przerwy,
+// tak jak na liście powrotów do pracy
+pub fn find_candidates<'a>(
+    data: &'a [EmployeeData],
+    today: NaiveDate,
+    criteria: &ControlCriteria,
+) -> Vec<ControlCandidate<'a>> {
+    let lookback_start = today - Duration::days(criteria.lookback_days);
+
+    let mut ends_by_pesel: HashMap<&str, Vec<NaiveDate>> = HashMap::new();
+    for employee in data.iter().filter(|employee| !employee.is_cancelled()) {
+        if let Some((_, end)) = employee.okres() {
+            ends_by_pesel.entry(&employee.pesel).or_default().push(end);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for episode in episodes::build_episodes(data, 0) {
+        // Kontrolować można tylko osobę, która jest obecnie na zwolnieniu
+        if episode.start > today || episode.end < today {
+            continue;
+        }
+        // Bieżące zaświadczenie obejmuje dzisiejszy dzień; z nakładających się - rozpoczęte najpóźniej
+        let Some((_, _, current)) = episode
+            .zaswiadczenia
+            .iter()
+            .filter_map(|employee| employee.okres().map(|(start, end)| (start, end, *employee)))
+            .filter(|(start, end, _)| *start <= today && *end >= today)
+            .max_by_key(|(start, end, employee)| (*start, *end, employee.wiersz))
+        else {
+            continue;
+        };
+
+        if criteria.only_bed_rest && instruction_code(&current.wskazania) != BED_REST_CODE {
+            continue;
+        }
+
+        let dni = episode.dni();
+        let liczba_zaswiadczen = ends_by_pesel
+            .get(current.pesel.as_str())
+            .map(|ends| ends.iter().filter(|end| **end >= lookback_start).count())
+            .unwrap_or_default();
+
+        let mut reasons = Vec::new();
+        if dni >= criteria.min_days {
+            reasons.push(format!("zwolnienie {} dni", dni));
+        }
+        if liczba_zaswiadczen >= criteria.min_certificates {
+            reasons.push(format!(
+                "{} zaświadczeń w ostatnich {} dniach",
+                liczba_zaswiadczen, criteria.lookback_days
+            ));
+        }
+        if reasons.is_empty() {
+            continue;
+        }
+
+        candidates.push(ControlCandidate {
+            employee: current,
+            data_od: episode.start,
+            data_do: episode.end,
+            dni,
+            liczba_zaswiadczen,
+            powod: reasons.join(", "),
+        });
+    }
+
+    candidates.sort_by(|a, b| {
+        a.employee
+            .nazwisko
+            .cmp(&b.employee.nazwisko)
+            .then_with(|| a.employee.pesel.cmp(&b.employee.pesel))
+    });
+    candidates
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    candidates: &[ControlCandidate],
+    criteria: &ControlCriteria,
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Kontrole")?;
+
+    let headers = [
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Dział",
+        "Seria i nr zaśw.",
+        "Data od",
+        "Data do",
+        "Dni",
+        "Liczba zaśw.",
+        "Kod wskazań",
+        "Wskazania lekarskie",
+        "Powód",
+    ];
+    let widths = [20.0, 15.0, 12.0, 16.0, 16.0, 12.0, 12.0, 6.0, 12.0, 12.0, 28.0, 40.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, candidate) in candidates.iter().enumerate() {
+        let row = idx as u32 + 1;
+        let employee = candidate.employee;
+        sheet.write_string(row, 0, privacy.name(&employee.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&employee.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&employee.pesel))?;
+        sheet.write_string(row, 3, &employee.dzial)?;
+        sheet.write_string(row, 4, &employee.nr_zaswiadczenia)?;
+        sheet.write_number_with_format(row, 5, date_to_excel(candidate.data_od), date_format)?;
+        sheet.write_number_with_format(row, 6, date_to_excel(candidate.data_do), date_format)?;
+        sheet.write_number(row, 7, candidate.dni as f64)?;
+        sheet.write_number(row, 8, candidate.liczba_zaswiadczen as f64)?;
+        sheet.write_string(row, 9, instruction_code(&employee.wskazania))?;
+        sheet.write_string(row, 10, &employee.wskazania)?;
+        sheet.write_string(row, 11, &candidate.powod)?;
+    }
+
+    let criteria_row = candidates.len() as u32 + 2;
+    sheet.write_string(
+        criteria_row,
+        0,
+        format!(
+            "Kryteria: zwolnienie od {} dni lub co najmniej {} zaświadczeń w ostatnich {} dniach{}",
+            criteria.min_days,
+            criteria.min_certificates,
+            criteria.lookback_days,
+            if criteria.only_bed_rest { ", tylko wskazanie \"chory powinien leżeć\"" } else { "" }
+        ),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn certificate(nr: &str, start: u32, end: u32, wskazania: &str) -> EmployeeData {
+        EmployeeData {
+            nazwisko: "Kowalski".to_string(),
+            pesel: "85010112345".to_string(),
+            nr_zaswiadczenia: nr.to_string(),
+            data_od: Some(date_to_excel(date(start))),
+            data_do: Some(date_to_excel(date(end))),
+            wskazania: wskazania.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn criteria() -> ControlCriteria {
+        ControlCriteria { min_certificates: 10, ..Default::default() }
+    }
+
+    #[test]
+    fn back_to_back_certificates_count_as_one_leave() {
+        let data = vec![certificate("ZUS1", 1, 10, ""), certificate("ZUS2", 11, 20, "")];
+        let candidates = find_candidates(&data, date(15), &criteria());
+        assert_eq!(candidates.len(), 1);
+        assert_eq!((candidates[0].data_od, candidates[0].data_do, candidates[0].dni), (date(1), date(20), 20));
+        assert_eq!(candidates[0].employee.nr_zaswiadczenia, "ZUS2");
+    }
+
+    #[test]
+    fn leave_after_a_gap_is_counted_from_its_start() {
+        let data = vec![certificate("ZUS1", 1, 10, ""), certificate("ZUS2", 12, 20, "")];
+        assert!(find_candidates(&data, date(15), &criteria()).is_empty());
+    }
+
+    #[test]
+    fn overlapping_certificates_use_the_latest_one() {
+        let data = vec![
+            certificate("ZUS2", 5, 20, "1 - chory powinien leżeć"),
+            certificate("ZUS1", 1, 18, "2 - chory może chodzić"),
+        ];
+        let candidates = find_candidates(&data, date(10), &criteria());
+        assert_eq!(candidates[0].employee.nr_zaswiadczenia, "ZUS2");
+        assert_eq!(candidates[0].dni, 20);
+
+        let bed_rest = ControlCriteria { only_bed_rest: true, ..criteria() };
+        assert_eq!(find_candidates(&data, date(10), &bed_rest).len(), 1);
+    }
+}
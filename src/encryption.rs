@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
+
+// Zmienna środowiskowa z hasłem do szyfrowania plików w trybie wiersza poleceń
+pub const PASSWORD_VARIABLE: &str = "L4_HASLO";
+
+pub fn password_from_env() -> Result<String> {
+    match std::env::var(PASSWORD_VARIABLE) {
+        Ok(password) if !password.is_empty() => Ok(password),
+        _ => bail!("Szyfrowanie wymaga hasła w zmiennej środowiskowej {}", PASSWORD_VARIABLE),
+    }
+}
+
+// Plik zapisywany na dysk: bez hasła - bezpośrednio, z hasłem - jako archiwum ZIP szyfrowane AES-256
+// z dopisanym rozszerzeniem .zip. Archiwum powstaje w pamięci, więc niezaszyfrowana treść nie trafia na dysk.
+// Zwraca ścieżkę zapisanego pliku.
+pub fn write_file(path: &Path, bytes: &[u8], password: Option<&str>) -> Result<PathBuf> {
+    let Some(password) = password else {
+        write_atomic(path, bytes)?;
+        return Ok(path.to_path_buf());
+    };
+    if password.is_empty() {
+        bail!("Hasło do szyfrowania nie może być puste");
+    }
+
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .with_aes_encryption(AesMode::Aes256, password);
+    writer.start_file(name, options)?;
+    writer.write_all(bytes)?;
+    let archive = writer.finish()?.into_inner();
+
+    let archive_path = target_path(path, true);
+    write_atomic(&archive_path, &archive)?;
+    Ok(archive_path)
+}
+
+// Odczyt pliku zapisanego przez write_file: archiwum .zip jest odszyfrowywane hasłem, inny plik - czytany wprost
+pub fn read_file(path: &Path, password: Option<&str>) -> Result<Vec<u8>> {
+    if !is_encrypted(path) {
+        return std::fs::read(path).with_context(|| format!("Nie można odczytać pliku {}", path.display()));
+    }
+    let Some(password) = password.filter(|password| !password.is_empty()) else {
+        bail!("Plik {} jest zaszyfrowany - podaj hasło", path.display());
+    };
+
+    let file = std::fs::File::open(path).with_context(|| format!("Nie można odczytać pliku {}", path.display()))?;
+    let mut archive = ZipArchive::new(file).with_context(|| format!("Niepoprawne archiwum ZIP {}", path.display()))?;
+    let mut entry = archive
+        .by_index_decrypt(0, password.as_bytes())
+        .with_context(|| format!("Nie można odszyfrować pliku {} - niepoprawne hasło?", path.display()))?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+// Zapis do pliku tymczasowego w tym samym katalogu i zamiana nazwy - przerwany zapis
+// nie zostawia uszkodzonego pliku w miejscu poprzedniego
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    std::fs::write(&temp, bytes).with_context(|| format!("Nie można zapisać pliku {}", temp.display()))?;
+    if let Err(e) = std::fs::rename(&temp, path) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e).with_context(|| format!("Nie można zapisać pliku {}", path.display()));
+    }
+    Ok(())
+}
+
+// Ścieżka, pod którą trafi plik: archiwum zachowuje pełną nazwę pliku (raport.xlsx -> raport.xlsx.zip),
+// żeby pliki różniące się tylko rozszerzeniem (np. raport.xlsx i raport.csv) nie trafiały do jednego archiwum
+pub fn target_path(path: &Path, encrypted: bool) -> PathBuf {
+    if !encrypted {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".zip");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pusty katalog tymczasowy tego testu
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("l4-filter-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn encrypted_file_round_trip() {
+        let dir = test_dir("szyfrowanie");
+        let path = dir.join("raport.xlsx");
+        let written = write_file(&path, b"tajne dane", Some("haslo")).unwrap();
+
+        assert_eq!(written, dir.join("raport.xlsx.zip"));
+        assert_eq!(file_names(&dir), ["raport.xlsx.zip"]);
+        assert_eq!(read_file(&written, Some("haslo")).unwrap(), b"tajne dane");
+        assert!(read_file(&written, Some("inne haslo")).is_err());
+        assert!(read_file(&written, None).is_err());
+
+        let mut archive = ZipArchive::new(std::fs::File::open(&written).unwrap()).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.name_for_index(0), Some("raport.xlsx"));
+        assert!(archive.by_index(0).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plain_file_is_written_without_temporary_file() {
+        let dir = test_dir("bez-szyfrowania");
+        let path = dir.join("raport.csv");
+        assert_eq!(write_file(&path, b"a;b", None).unwrap(), path);
+        assert_eq!(file_names(&dir), ["raport.csv"]);
+        assert_eq!(read_file(&path, None).unwrap(), b"a;b");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_password_is_rejected() {
+        let dir = test_dir("puste-haslo");
+        assert!(write_file(&dir.join("raport.xlsx"), b"tajne dane", Some("")).is_err());
+        assert!(file_names(&dir).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
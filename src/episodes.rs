@@ -0,0 +1,230 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::excel_merger::{date_to_excel, EmployeeData};
+use crate::privacy::Privacy;
+
+// Ciągła nieobecność złożona z jednego lub kilku kolejnych zaświadczeń tej samej osoby
+#[derive(Debug, Clone)]
+pub struct Episode<'a> {
+    pub employee: &'a EmployeeData,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub zaswiadczenia: Vec<&'a EmployeeData>,
+}
+
+impl Episode<'_> {
+    // Liczba dni od początku do końca epizodu, łącznie z ewentualnymi przerwami
+    pub fn dni(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    // Liczba dni faktycznie objętych zaświadczeniami
+    pub fn dni_zwolnien(&self) -> i64 {
+        covered_days(&self.zaswiadczenia, |_| true)
+    }
+
+    // Liczba dni objętych zaświadczeniami z powodu własnej choroby (bez opieki)
+    pub fn dni_choroby(&self) -> i64 {
+        covered_days(&self.zaswiadczenia, |employee| !employee.is_care())
+    }
+
+    pub fn numery(&self) -> String {
+        self.zaswiadczenia
+            .iter()
+            .map(|employee| employee.nr_zaswiadczenia.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn covered_days(certificates: &[&EmployeeData], include: impl Fn(&EmployeeData) -> bool) -> i64 {
+    let days: BTreeSet<NaiveDate> = certificates
+        .iter()
+        .filter(|employee| include(employee))
+        .filter_map(|employee| employee.okres())
+        .flat_map(|(start, end)| start.iter_days().take_while(move |date| *date <= end))
+        .collect();
+    days.len() as i64
+}
+
+// Łączy nieanulowane zaświadczenia tej samej osoby, które na siebie nachodzą, stykają się
+// albo są oddzielone przerwą nie dłuższą niż `gap_tolerance_days` dni
+pub fn build_episodes(data: &[EmployeeData], gap_tolerance_days: u32) -> Vec<Episode<'_>> {
+    let mut by_pesel: HashMap<&str, Vec<(NaiveDate, NaiveDate, &EmployeeData)>> = HashMap::new();
+    for employee in data.iter().filter(|employee| !employee.is_cancelled()) {
+        if let Some((start, end)) = employee.okres() {
+            if start <= end {
+                by_pesel.entry(&employee.pesel).or_default().push((start, end, employee));
+            }
+        }
+    }
+
+    let max_gap = Duration::days(gap_tolerance_days as i64 + 1);
+    let mut episodes: Vec<Episode> = Vec::new();
+
+    for periods in by_pesel.values_mut() {
+        periods.sort_by_key(|(start, end, _)| (*start, *end));
+
+        let mut person_episodes: Vec<Episode> = Vec::new();
+        for &(start, end, employee) in periods.iter() {
+            match person_episodes.last_mut() {
+                Some(episode) if start <= episode.end + max_gap => {
+                    episode.end = episode.end.max(end);
+                    episode.zaswiadczenia.push(employee);
+                }
+                _ => person_episodes.push(Episode {
+                    employee,
+                    start,
+                    end,
+                    zaswiadczenia: vec![employee],
+                }),
+            }
+        }
+        episodes.extend(person_episodes);
+    }
+
+    episodes.sort_by(|a, b| {
+        a.employee
+            .nazwisko
+            .cmp(&b.employee.nazwisko)
+            .then_with(|| a.employee.pesel.cmp(&b.employee.pesel))
+            .then_with(|| a.start.cmp(&b.start))
+    });
+    episodes
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    episodes: &[Episode],
+    gap_tolerance_days: u32,
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Epizody")?;
+
+    let headers = [
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Początek",
+        "Koniec",
+        "Dni łącznie",
+        "Dni zwolnień",
+        "Liczba zaśw.",
+        "Zaświadczenia",
+    ];
+    let widths = [20.0, 15.0, 12.0, 12.0, 12.0, 11.0, 12.0, 12.0, 50.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, episode) in episodes.iter().enumerate() {
+        let row = idx as u32 + 1;
+        sheet.write_string(row, 0, privacy.name(&episode.employee.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&episode.employee.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&episode.employee.pesel))?;
+        sheet.write_number_with_format(row, 3, date_to_excel(episode.start), date_format)?;
+        sheet.write_number_with_format(row, 4, date_to_excel(episode.end), date_format)?;
+        sheet.write_number(row, 5, episode.dni() as f64)?;
+        sheet.write_number(row, 6, episode.dni_zwolnien() as f64)?;
+        sheet.write_number(row, 7, episode.zaswiadczenia.len() as f64)?;
+        sheet.write_string(row, 8, episode.numery())?;
+    }
+
+    sheet.write_string(
+        episodes.len() as u32 + 2,
+        0,
+        format!("Dopuszczalna przerwa między zaświadczeniami: {} dni", gap_tolerance_days),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn certificate(pesel: &str, nr: &str, start: u32, end: u32) -> EmployeeData {
+        EmployeeData {
+            nazwisko: format!("Nazwisko {}", pesel),
+            pesel: pesel.to_string(),
+            nr_zaswiadczenia: nr.to_string(),
+            data_od: Some(date_to_excel(date(start))),
+            data_do: Some(date_to_excel(date(end))),
+            ..Default::default()
+        }
+    }
+
+    fn spans(episodes: &[Episode]) -> Vec<(NaiveDate, NaiveDate, String)> {
+        episodes.iter().map(|episode| (episode.start, episode.end, episode.numery())).collect()
+    }
+
+    #[test]
+    fn contiguous_certificates_form_one_episode() {
+        let data = vec![certificate("1", "ZUS2", 11, 20), certificate("1", "ZUS1", 1, 10)];
+        let episodes = build_episodes(&data, 0);
+        assert_eq!(spans(&episodes), [(date(1), date(20), "ZUS1, ZUS2".to_string())]);
+        assert_eq!((episodes[0].dni(), episodes[0].dni_zwolnien()), (20, 20));
+    }
+
+    #[test]
+    fn overlapping_certificates_are_counted_once() {
+        let data = vec![certificate("1", "ZUS1", 1, 10), certificate("1", "ZUS2", 5, 15), certificate("1", "ZUS3", 6, 8)];
+        let episodes = build_episodes(&data, 0);
+        assert_eq!(spans(&episodes), [(date(1), date(15), "ZUS1, ZUS2, ZUS3".to_string())]);
+        assert_eq!((episodes[0].dni(), episodes[0].dni_zwolnien()), (15, 15));
+    }
+
+    #[test]
+    fn gap_within_tolerance_joins_certificates() {
+        let data = vec![certificate("1", "ZUS1", 1, 10), certificate("1", "ZUS2", 13, 20)];
+        assert_eq!(build_episodes(&data, 0).len(), 2);
+
+        let episodes = build_episodes(&data, 2);
+        assert_eq!(spans(&episodes), [(date(1), date(20), "ZUS1, ZUS2".to_string())]);
+        assert_eq!((episodes[0].dni(), episodes[0].dni_zwolnien()), (20, 18));
+    }
+
+    #[test]
+    fn gap_beyond_tolerance_starts_a_new_episode() {
+        let data = vec![certificate("1", "ZUS1", 1, 10), certificate("1", "ZUS2", 14, 20)];
+        assert_eq!(
+            spans(&build_episodes(&data, 2)),
+            [(date(1), date(10), "ZUS1".to_string()), (date(14), date(20), "ZUS2".to_string())]
+        );
+        assert_eq!(build_episodes(&data, 3).len(), 1);
+    }
+
+    #[test]
+    fn cancelled_certificates_and_other_people_are_separate() {
+        let mut cancelled = certificate("1", "ZUS2", 11, 20);
+        cancelled.status = "Anulowane".to_string();
+        let data = vec![certificate("1", "ZUS1", 1, 10), cancelled, certificate("1", "ZUS3", 21, 25), certificate("2", "ZUS4", 11, 20)];
+        assert_eq!(
+            spans(&build_episodes(&data, 0)),
+            [
+                (date(1), date(10), "ZUS1".to_string()),
+                (date(21), date(25), "ZUS3".to_string()),
+                (date(11), date(20), "ZUS4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn care_days_are_not_illness_days() {
+        let mut care = certificate("1", "ZUS2", 8, 12);
+        care.na_opieke = "Tak".to_string();
+        let data = vec![certificate("1", "ZUS1", 1, 10), care];
+        let episodes = build_episodes(&data, 0);
+        assert_eq!((episodes[0].dni_zwolnien(), episodes[0].dni_choroby()), (12, 10));
+    }
+}
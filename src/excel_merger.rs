@@ -1,12 +1,33 @@
-use std::path::PathBuf;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Result};
 use calamine::{Reader, Xlsx, Xls, open_workbook, DataType};
 use regex::Regex;
-use std::collections::HashSet;
-use rust_xlsxwriter::{Workbook, Format, FormatBorder, Color, Worksheet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use rust_xlsxwriter::Workbook;
 use std::error::Error;
+use chrono::{Datelike, NaiveDate};
+use crate::attendance;
+use crate::changes::{self, ChangesSource};
+use crate::compliance;
+use crate::control::{self, ControlCriteria};
+use crate::encryption;
+use crate::episodes;
+use crate::history::{self, Import, PreviousImport};
+use crate::ics::IcsOptions;
+use crate::layout::ReportLayout;
+use crate::manifest::{self, FileHash, Manifest};
+use crate::anomalies;
+use crate::output::{self, CsvOptions, OutputFormat, Report};
+use crate::payroll::{self, PayrollOptions};
+use crate::privacy::{Privacy, PrivacyMode};
+use crate::return_to_work;
+use crate::snapshot::{self, AbsentEmployee};
+use crate::split::{self, SplitFile, SplitOptions};
+use crate::statistics;
+use crate::summary::{self, Summary};
+use crate::template;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct EmployeeData {
     pub nazwisko: String,
     pub imie: String,
@@ -17,16 +38,172 @@ pub struct EmployeeData {
     pub pobyt_w_szpitalu: String,
     pub status: String,
     pub source: String,
+    pub dzial: String,
+    pub nr_zaswiadczenia: String,
+    pub data_wystawienia: Option<f64>,
+    pub data_zatrudnienia: Option<f64>,
+    pub wskazania: String,
+    // Numer wiersza w arkuszu źródłowym (numeracja jak w Excelu)
+    pub wiersz: usize,
+    // Wybrane dodatkowe kolumny z listy pracowników (nagłówek, wartość)
+    pub dodatkowe: Vec<(String, String)>,
+}
+
+impl EmployeeData {
+    pub fn okres(&self) -> Option<(NaiveDate, NaiveDate)> {
+        Some((excel_to_date(self.data_od?)?, excel_to_date(self.data_do?)?))
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.status.to_lowercase().contains("anul")
+    }
+
+    pub fn is_care(&self) -> bool {
+        is_yes(&self.na_opieke)
+    }
+
+    pub fn dodatkowa(&self, name: &str) -> Option<&str> {
+        self.dodatkowe
+            .iter()
+            .find(|(header, _)| header.to_lowercase() == name.trim().to_lowercase())
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// Kolumny "Na opiekę" i "Pobyt w szpitalu" zawierają "Tak"/"Nie" albo "T"/"N"
+pub fn is_yes(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "tak" | "t")
+}
+
+pub fn excel_to_date(excel_date: f64) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(1900, 1, 1)?.checked_add_signed(chrono::Duration::days(excel_date as i64 - 2))
+}
+
+pub fn date_to_excel(date: NaiveDate) -> f64 {
+    (date - NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()).num_days() as f64 + 2.0
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    // Liczba dni naprzód dla arkusza powrotów do pracy (None - arkusz nie jest tworzony)
+    pub return_window_days: Option<u32>,
+    // Rok i miesiąc arkusza z listą obecności (None - arkusz nie jest tworzony)
+    pub attendance_month: Option<(i32, u32)>,
+    // Okres, za który liczone są wskaźniki absencji (None - arkusz "Statystyki" nie jest tworzony)
+    pub statistics_period: Option<(NaiveDate, NaiveDate)>,
+    // Dodatkowy zapis statystyk do pliku JSON
+    pub statistics_json: Option<PathBuf>,
+    // Sprawdzanie zaświadczeń pod kątem błędów w danych (arkusz "Anomalie")
+    pub check_anomalies: bool,
+    // Kryteria listy osób do kontroli zwolnień (None - arkusz "Kontrole" nie jest tworzony)
+    pub control_criteria: Option<ControlCriteria>,
+    // Dopuszczalna przerwa w dniach przy łączeniu zaświadczeń w epizody (None - arkusz "Epizody" nie jest tworzony)
+    pub episode_gap_days: Option<u32>,
+    // Nagłówki kolumn z listy pracowników dopisywane na końcu każdego wiersza raportu
+    pub extra_columns: Vec<String>,
+    // Podział raportu na osobne pliki według wartości kolumny z listy pracowników
+    pub split: Option<SplitOptions>,
+    // Wykresy w arkuszu "Podsumowanie"
+    pub charts: bool,
+    // Kolumny, ich kolejność, nagłówki i formaty arkusza z danymi
+    pub layout: ReportLayout,
+    // Skoroszyt z szablonem firmowym, do którego wpisywane są dane (None - nowy skoroszyt)
+    pub template: Option<PathBuf>,
+    // Dopisanie arkusza z danymi (nazwa RRRR-MM według okresu) do istniejącego pliku wynikowego
+    pub append: bool,
+    // Zastąpienie arkusza o tej samej nazwie w trybie dopisywania
+    pub replace_sheet: bool,
+    // Format pliku wynikowego (None - według rozszerzenia pliku)
+    pub format: Option<OutputFormat>,
+    pub csv: CsvOptions,
+    // Nazwa firmy w nagłówku raportu PDF i HTML
+    pub company: String,
+    pub ics: IcsOptions,
+    // Dodatkowy plik z nieobecnościami dla programu płacowego (None - plik nie jest tworzony)
+    pub payroll: Option<PayrollOptions>,
+    // Zakres danych osobowych w raporcie (plik płacowy zawsze zawiera pełne dane)
+    pub privacy: PrivacyMode,
+    // Hasło szyfrowania plików wynikowych (None - pliki bez szyfrowania)
+    pub password: Option<String>,
+    // Zapis zaświadczeń z uruchomienia w lokalnej bazie historii
+    pub history: bool,
+    // Porównanie z poprzednim uruchomieniem albo wcześniejszym raportem (None - arkusz "Zmiany" nie jest tworzony)
+    pub changes: Option<ChangesSource>,
+}
+
+impl MergeOptions {
+    // Okres raportu: okres statystyk, miesiąc listy obecności albo bieżący miesiąc
+    pub fn period(&self) -> (NaiveDate, NaiveDate) {
+        if let Some(period) = self.statistics_period {
+            return period;
+        }
+        let (year, month) = self.attendance_month.unwrap_or_else(|| {
+            let today = chrono::Local::now().date_naive();
+            (today.year(), today.month())
+        });
+        let start = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+        let end = start
+            .checked_add_months(chrono::Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(start);
+        (start, end)
+    }
+}
+
+// Możliwe nagłówki kolumn opcjonalnych w pliku pracowników
+const DEPARTMENT_HEADERS: [&str; 4] = ["dział", "dzial", "departament", "wydział"];
+const EMPLOYMENT_DATE_HEADERS: [&str; 4] = ["data zatrudnienia", "zatrudniony od", "data przyjęcia", "data przyjecia"];
+// Kolumna ze wskazaniami lekarskimi w eksporcie z PUE ZUS
+const INSTRUCTION_HEADERS: [&str; 3] = ["wskazania lekarskie", "wskazania", "wskazanie lekarskie"];
+
+fn is_empty_row(row: &[DataType]) -> bool {
+    row.iter().all(|cell| cell.is_empty() || cell.to_string().trim().is_empty())
+}
+
+fn find_column(header_row: &[DataType], names: &[&str]) -> Option<usize> {
+    header_row.iter().position(|cell| {
+        let header = cell.to_string().trim().to_lowercase();
+        names.contains(&header.as_str())
+    })
+}
+
+// Tekst komórki do kolumn dodatkowych - daty w formacie DD.MM.RRRR zamiast liczby Excela
+fn cell_to_text(cell: &DataType) -> String {
+    match cell {
+        DataType::DateTime(value) => excel_to_date(*value)
+            .map(|date| date.format("%d.%m.%Y").to_string())
+            .unwrap_or_default(),
+        _ => cell.to_string().trim().to_string(),
+    }
+}
+
+pub struct MatchedData {
+    // Kolumny z listy pracowników znalezione w pliku (nagłówki jak w pliku)
+    pub columns: Vec<String>,
+    // Wszyscy pracownicy z listy pracowników
+    pub employees: Vec<EmployeeData>,
+    // Wiersze L4 osób obecnych na liście pracowników
+    pub matched: Vec<EmployeeData>,
+    // Wiersze L4 osób spoza listy pracowników
+    pub unmatched: Vec<EmployeeData>,
+    // Niepuste wiersze obu plików, których nie udało się odczytać
+    pub skipped: usize,
 }
 
 pub struct ExcelMerger {
     pub messages: Vec<String>,
+    // Pliki zapisane w bieżącym uruchomieniu (rola, ścieżka) - do manifestu
+    written: Vec<(&'static str, PathBuf)>,
+    // Sumy kontrolne plików wejściowych bieżącego uruchomienia - do manifestu
+    inputs: Vec<FileHash>,
 }
 
 impl ExcelMerger {
     pub fn new() -> Self {
         Self {
             messages: Vec::new(),
+            written: Vec::new(),
+            inputs: Vec::new(),
         }
     }
 
@@ -34,6 +211,26 @@ impl ExcelMerger {
         self.messages.push(message);
     }
 
+    // Zapis pliku wynikowego (zaszyfrowanego, jeśli podano hasło) z zapamiętaniem go do manifestu.
+    // Plik zapisany już w tym uruchomieniu nie jest nadpisywany.
+    fn write_output(&mut self, rola: &'static str, path: &Path, bytes: &[u8], password: Option<&str>) -> Result<PathBuf> {
+        let target = encryption::target_path(path, password.is_some());
+        if let Some((earlier, _)) = self.written.iter().find(|(_, file)| *file == target) {
+            bail!(
+                "Plik {} został już zapisany w tym uruchomieniu ({}) - wybierz inną nazwę pliku",
+                target.display(),
+                earlier
+            );
+        }
+        let file = encryption::write_file(path, bytes, password)?;
+        self.written.push((rola, file.clone()));
+        Ok(file)
+    }
+
+    fn input_sha256(&self, rola: &str) -> String {
+        self.inputs.iter().find(|input| input.rola == rola).map(|input| input.sha256.clone()).unwrap_or_default()
+    }
+
     pub fn get_sheet_names(&mut self, path: &PathBuf) -> Vec<String> {
         let extension = path.extension()
             .and_then(|ext| ext.to_str())
@@ -74,6 +271,40 @@ impl ExcelMerger {
         }
     }
 
+    // Niepuste nagłówki z pierwszego wiersza arkusza (numer kolumny, nazwa), do wyboru kolumn dodatkowych
+    pub fn get_column_names(&mut self, path: &PathBuf, sheet: &str) -> Vec<(usize, String)> {
+        match Self::read_range(path, sheet) {
+            Ok(range) => range
+                .rows()
+                .next()
+                .map(|header_row| {
+                    header_row
+                        .iter()
+                        .map(|cell| cell.to_string().trim().to_string())
+                        .enumerate()
+                        .filter(|(_, header)| !header.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Err(e) => {
+                self.log_message(format!("Błąd podczas wczytywania nagłówków: {}", e));
+                Vec::new()
+            }
+        }
+    }
+
+    fn read_range(path: &PathBuf, sheet: &str) -> Result<calamine::Range<DataType>> {
+        if path.extension().and_then(|ext| ext.to_str()).map(|s| s.to_lowercase()) == Some("xlsx".to_string()) {
+            let mut workbook: Xlsx<_> = open_workbook(path)?;
+            Ok(workbook.worksheet_range(sheet)
+                .ok_or_else(|| anyhow::anyhow!("Nie można otworzyć arkusza {}", sheet))??)
+        } else {
+            let mut workbook: Xls<_> = open_workbook(path)?;
+            Ok(workbook.worksheet_range(sheet)
+                .ok_or_else(|| anyhow::anyhow!("Nie można otworzyć arkusza {}", sheet))??)
+        }
+    }
+
     fn parse_date(date_str: &str) -> Option<f64> {
         if date_str.is_empty() {
             return None;
@@ -88,11 +319,20 @@ impl ExcelMerger {
         let month = parts[1].parse::<u32>().ok()?;
         let day = parts[2].parse::<u32>().ok()?;
 
-        if let Some(date) = chrono::NaiveDate::from_ymd_opt(year, month, day) {
-            let excel_date = (date - chrono::NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()).num_days() as f64 + 2.0;
-            Some(excel_date)
-        } else {
-            None
+        NaiveDate::from_ymd_opt(year, month, day).map(date_to_excel)
+    }
+
+    // Data z komórki zapisanej jako data Excela albo tekst w formacie RRRR-MM-DD lub DD.MM.RRRR
+    pub fn cell_to_date(cell: &DataType) -> Option<f64> {
+        match cell {
+            DataType::DateTime(value) | DataType::Float(value) => Some(value.trunc()),
+            DataType::String(text) => {
+                let text = text.trim();
+                Self::parse_date(text).or_else(|| {
+                    NaiveDate::parse_from_str(text, "%d.%m.%Y").ok().map(date_to_excel)
+                })
+            }
+            _ => None,
         }
     }
 
@@ -100,47 +340,555 @@ impl ExcelMerger {
         &mut self,
         first_file: &PathBuf,
         second_file: &PathBuf,
-        output_file: &PathBuf,
+        output_file: &Path,
         first_sheet: &str,
         second_sheet: &str,
+        options: &MergeOptions,
     ) -> Result<(), Box<dyn Error>> {
-        let mut workbook = Workbook::new();
-        let mut sheet = workbook.add_worksheet();
-        
-        let header_format = Format::new()
-            .set_bold()
-            .set_background_color(Color::RGB(0x4F8_1BD))
-            .set_font_color(Color::RGB(0xFFFF_FF))
-            .set_border(FormatBorder::Thin);
-            
-        let date_format = Format::new().set_num_format("dd/mm/yyyy");
-        
-        let (headers, data) = self.prepare_data(first_file, second_file, first_sheet, second_sheet)?;
-        let common_pesels = self.find_common_pesels(&data);
-        
-        let filtered_data: Vec<EmployeeData> = data.into_iter()
-            .filter(|employee| employee.source == "l4" && common_pesels.contains(&employee.pesel))
-            .collect();
-        
-        self.write_headers(&mut sheet, &headers, &header_format)?;
-        self.write_data(&mut sheet, &filtered_data, &date_format)?;
-        
-        workbook.save(output_file)?;
+        let started = chrono::Local::now();
+        if let Some((start, end)) = options.statistics_period {
+            if start > end {
+                return Err(format!(
+                    "Początek okresu statystyk ({}) jest po jego końcu ({})",
+                    start.format("%d.%m.%Y"),
+                    end.format("%d.%m.%Y")
+                )
+                .into());
+            }
+        }
+        if options.append && options.template.is_some() {
+            return Err("Trybu dopisywania nie można łączyć z szablonem".into());
+        }
+        if options.password.as_deref().is_some_and(str::is_empty) {
+            return Err("Hasło do szyfrowania nie może być puste".into());
+        }
+        if options.append && options.password.is_some() {
+            return Err("Trybu dopisywania nie można łączyć z szyfrowaniem pliku wynikowego".into());
+        }
+        // Baza historii nie jest szyfrowana, więc nie może przechowywać danych z szyfrowanych raportów
+        if options.history && options.password.is_some() {
+            return Err("Zapisu w historii nie można łączyć z szyfrowaniem - baza historii nie jest szyfrowana".into());
+        }
+        let format = options.format.unwrap_or_else(|| OutputFormat::from_path(output_file));
+        if format != OutputFormat::Xlsx && (options.append || options.template.is_some()) {
+            return Err("Szablon i tryb dopisywania są dostępne tylko dla plików XLSX".into());
+        }
+        if !options.privacy.has_rows() {
+            if !matches!(format, OutputFormat::Xlsx | OutputFormat::Pdf | OutputFormat::Html) {
+                return Err("Raport zanonimizowany można zapisać tylko w formacie XLSX, PDF lub HTML".into());
+            }
+            if options.split.is_some() || options.append || options.template.is_some() {
+                return Err("Raportu zanonimizowanego nie można dzielić, dopisywać ani wpisywać do szablonu".into());
+            }
+        }
+        let privacy = Privacy::new(options.privacy)?;
+
+        let mut layout = options.layout.clone();
+        for name in layout.add_roster_columns(&options.extra_columns) {
+            self.log_message(format!("Kolumna {} jest już w układzie {}", name, layout.nazwa));
+        }
+        layout.validate()?;
+
+        // Kolumna podziału musi zostać wczytana razem z kolumnami układu
+        let mut roster_columns = layout.roster_columns();
+        if let Some(split) = &options.split {
+            if !roster_columns.iter().any(|name| name.trim().to_lowercase() == split.column.trim().to_lowercase()) {
+                roster_columns.push(split.column.clone());
+            }
+        }
+        if let Some(payroll) = &options.payroll {
+            payroll.profile.validate()?;
+            let column = &payroll.profile.kolumna_numeru;
+            if !roster_columns.iter().any(|name| name.trim().to_lowercase() == column.trim().to_lowercase()) {
+                roster_columns.push(column.clone());
+            }
+        }
+
+        // Sumy kontrolne plików wejściowych liczone przed wczytaniem danych
+        // (także skoroszytu, do którego dopisywany jest arkusz, zanim zostanie nadpisany)
+        self.written.clear();
+        self.inputs = vec![
+            FileHash::new("pracownicy", first_file, Some(first_sheet))?,
+            FileHash::new("l4", second_file, Some(second_sheet))?,
+        ];
+        if let Some(template_file) = &options.template {
+            self.inputs.push(FileHash::new("szablon", template_file, None)?);
+        }
+        if options.append && output_file.exists() {
+            self.inputs.push(FileHash::new("dopisywany", output_file, None)?);
+        }
+        // Pliki ustawień, z których mogły pochodzić układ i profil płacowy
+        let mut config_files = vec![("uklady", crate::layout::layouts_file())];
+        if options.payroll.is_some() {
+            config_files.push(("profile_placowe", payroll::profiles_file()));
+        }
+        for (rola, file) in config_files {
+            if file.exists() {
+                self.inputs.push(FileHash::new(rola, &file, None)?);
+            }
+        }
+        let data = self.load_matched_data(first_file, second_file, first_sheet, second_sheet, &roster_columns)?;
+        let report_summary = summary::compute(&data);
+        let MatchedData { columns, employees, matched: filtered_data, unmatched, .. } = data;
+        if let Some(payroll) = &options.payroll {
+            let column = payroll.profile.kolumna_numeru.trim().to_lowercase();
+            if !columns.iter().any(|header| header.trim().to_lowercase() == column) {
+                return Err(format!(
+                    "Nie znaleziono kolumny numeru ewidencyjnego {} (profil płacowy {}) w pliku pracowników",
+                    payroll.profile.kolumna_numeru, payroll.profile.nazwa
+                )
+                .into());
+            }
+        }
+        let common_pesels: HashSet<&str> = filtered_data.iter().map(|employee| employee.pesel.as_str()).collect();
+        self.log_message(format!("Układ raportu: {}", layout.nazwa));
+        if options.privacy != PrivacyMode::Pelny {
+            self.log_message(format!("Dane osobowe w raporcie: {}", options.privacy.label()));
+        }
+
+        // Obliczenia korzystają z pełnych danych, do pliku trafiają wiersze w wybranym trybie
+        let rows = privacy.apply(&filtered_data);
+        let unmatched_rows = privacy.apply(&unmatched);
+        let anomaly_rows: BTreeSet<usize> = if options.check_anomalies {
+            anomalies::check(&filtered_data, chrono::Local::now().date_naive())
+                .iter()
+                .map(|anomaly| filtered_data[anomaly.index].wiersz)
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        let report = Report {
+            layout: &layout,
+            rows: &rows,
+            unmatched: &unmatched_rows,
+            privacy: options.privacy,
+            anomalies: &anomaly_rows,
+            summary: &report_summary,
+            period: options.period(),
+            started,
+            first_file,
+            second_file,
+        };
+        let bytes = if format == OutputFormat::Xlsx {
+            self.write_workbook(&report, &filtered_data, &employees, &privacy, output_file, options)?
+        } else {
+            if options.return_window_days.is_some()
+                || options.attendance_month.is_some()
+                || options.statistics_period.is_some()
+                || options.control_criteria.is_some()
+                || options.changes.is_some()
+                // W kalendarzu przerwa między zaświadczeniami służy do łączenia ich w epizody
+                || (options.episode_gap_days.is_some() && format != OutputFormat::Ics)
+            {
+                self.log_message(format!("Arkusze dodatkowe są dostępne tylko w formacie XLSX - pominięto je w pliku {}", format.label()));
+            }
+            output::writer(format, options).write(&report)?
+        };
+        if options.append && output_file.exists() {
+            let backup = template::backup(output_file)?;
+            self.log_message(format!("Kopia pliku sprzed dopisania arkusza: {}", backup.display()));
+        }
+        let written = self.write_output("wynik", output_file, &bytes, options.password.as_deref())?;
         
+        if report_summary.niedopasowane > 0 {
+            self.log_message(format!("\nWiersze L4 osób spoza listy pracowników: {}", report_summary.niedopasowane));
+        }
         let message = format!("\nLiczba wspólnych numerów PESEL: {}", common_pesels.len());
         self.log_message(message);
-        self.log_message(format!("Utworzono plik wynikowy: {}", output_file.display()));
+        self.log_message(format!("Utworzono plik wynikowy: {}", written.display()));
+        if options.password.is_some() {
+            self.log_message("Pliki wynikowe zaszyfrowano hasłem (archiwum ZIP, AES-256)".to_string());
+        }
+
+        if let Some(payroll) = &options.payroll {
+            self.write_payroll_file(&filtered_data, payroll, options.password.as_deref())?;
+        }
+
+        if let Some(split) = &options.split {
+            // Nazwa kolumny podziału tak, jak zapisano ją w pliku pracowników
+            let Some(column) = columns
+                .iter()
+                .find(|header| header.to_lowercase() == split.column.trim().to_lowercase())
+            else {
+                return Err(format!("Nie znaleziono kolumny podziału {} w pliku pracowników", split.column).into());
+            };
+            let split = SplitOptions { column: column.clone(), ..split.clone() };
+            let summaries = split::summaries(&filtered_data, &employees, &split.column);
+            self.write_split_files(output_file, &report, &summaries, &split, format, options)?;
+        }
+
+        if options.history {
+            let second_sha256 = self.input_sha256("l4");
+            let import = Import {
+                started,
+                first_file,
+                first_sheet,
+                second_file,
+                second_sheet,
+                second_sha256: &second_sha256,
+                period: report.period,
+                employees: employees.len(),
+                matched: &filtered_data,
+                unmatched: &unmatched,
+            };
+            // Pliki wynikowe są już zapisane, więc błąd bazy historii nie przerywa uruchomienia
+            match history::record(&import) {
+                Ok(result) => {
+                    self.log_message(format!(
+                        "\nZapisano w historii (import nr {}): nowe zaświadczenia: {}, zmienione: {}",
+                        result.id, result.nowe, result.zmienione
+                    ));
+                    if result.bez_numeru > 0 {
+                        self.log_message(format!("Zaświadczenia bez numeru pominięte w historii: {}", result.bez_numeru));
+                    }
+                }
+                Err(e) => self.log_message(format!("Nie zapisano historii: {}", e)),
+            }
+        }
+
+        // Manifest z sumami kontrolnymi plików wejściowych i wynikowych do celów audytu
+        let outputs = self
+            .written
+            .iter()
+            .map(|(rola, file)| FileHash::new(rola, file, None))
+            .collect::<Result<Vec<_>>>()?;
+        let manifest = Manifest::new(started, std::mem::take(&mut self.inputs), manifest::settings(options, format, &layout), outputs);
+        let manifest_file = manifest.save(&manifest::manifest_path(output_file), options.password.as_deref())?;
+        self.log_message(format!("Utworzono manifest z sumami kontrolnymi: {}", manifest_file.display()));
+
+        Ok(())
+    }
+
+    // Nieobecności w układzie profilu programu płacowego
+    fn write_payroll_file(
+        &mut self,
+        data: &[EmployeeData],
+        payroll: &PayrollOptions,
+        password: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let profile = &payroll.profile;
+        for employee in payroll::records(data) {
+            if profile.employee_number(employee).is_empty() {
+                self.log_message(format!(
+                    "Uwaga: pusty numer ewidencyjny (kolumna {}) w pliku płacowym - wiersz {} pliku L4, zaświadczenie {}",
+                    profile.kolumna_numeru, employee.wiersz, employee.nr_zaswiadczenia
+                ));
+            }
+        }
+        let file = self.write_output("place", &payroll.file, &payroll::write(profile, data)?, password)?;
+        self.log_message(format!(
+            "Utworzono plik płacowy ({}): {}, nieobecności: {}",
+            profile.nazwa,
+            file.display(),
+            payroll::records(data).len()
+        ));
+        Ok(())
+    }
+
+    // Pełny raport XLSX: podsumowanie, dane i arkusze dodatkowe wybrane w opcjach
+    fn write_workbook(
+        &mut self,
+        report: &Report,
+        data: &[EmployeeData],
+        employees: &[EmployeeData],
+        privacy: &Privacy,
+        output_file: &Path,
+        options: &MergeOptions,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut workbook = Workbook::new();
+        // Podsumowanie jest pierwszym arkuszem, ale wypełniane na końcu, gdy znany jest czas przetwarzania
+        workbook.add_worksheet().set_name(summary::SHEET_NAME)?;
         
+        let header_format = output::header_format();
+
+        if privacy.mode.has_rows() {
+            let sheet = workbook.add_worksheet();
+            sheet.set_name("Dane")?;
+            output::write_data_sheet(sheet, report.layout, report.rows, report.anomalies)?;
+            self.write_extra_sheets(&mut workbook, data, employees, privacy, options)?;
+        } else {
+            self.log_message("Raport zanonimizowany - pominięto arkusz z danymi i arkusze dodatkowe".to_string());
+        }
+
+        summary::write_sheet(
+            workbook.worksheet_from_index(0)?,
+            report,
+            // Wykresy nie przenoszą się poprawnie do skoroszytu z szablonem
+            options.charts && options.template.is_none(),
+            &header_format,
+        )?;
+
+        let bytes = match &options.template {
+            _ if options.append => {
+                let sheet_name = report.period.0.format("%Y-%m").to_string();
+                let book = template::append_sheet(
+                    output_file,
+                    &workbook.save_to_buffer()?,
+                    "Dane",
+                    &sheet_name,
+                    options.replace_sheet,
+                )?;
+                let existing = output_file.exists().then_some(output_file);
+                let bytes = template::to_bytes(&book, existing)?;
+                if let Some(existing) = existing {
+                    template::ensure_preserved(existing, &bytes)?;
+                }
+                self.log_message(format!("Dopisano arkusz {} (pozostałe arkusze raportu pominięto)", sheet_name));
+                bytes
+            }
+            Some(template_file) => {
+                let (period_start, period_end) = report.period;
+                let period = format!("{} - {}", period_start.format("%d.%m.%Y"), period_end.format("%d.%m.%Y"));
+                let placeholders = [
+                    ("{{okres}}", period.clone()),
+                    ("{{period}}", period),
+                    ("{{data}}", report.started.format("%d.%m.%Y").to_string()),
+                    ("{{liczba_wierszy}}", report.rows.len().to_string()),
+                    ("{{uklad}}", report.layout.nazwa.clone()),
+                ];
+                let mut book = template::fill(template_file, report.layout, report.rows, &placeholders)?;
+                for name in template::copy_sheets(&mut book, &workbook.save_to_buffer()?, &["Dane"])? {
+                    self.log_message(format!("Arkusz {} jest już w szablonie - pominięto", name));
+                }
+                self.log_message(format!("Szablon: {}", template_file.display()));
+                template::to_bytes(&book, Some(template_file))?
+            }
+            None => workbook.save_to_buffer()?,
+        };
+        Ok(bytes)
+    }
+
+    // Arkusze dodatkowe wybrane w opcjach; dane osobowe zapisywane w trybie raportu
+    fn write_extra_sheets(
+        &mut self,
+        workbook: &mut Workbook,
+        filtered_data: &[EmployeeData],
+        employees: &[EmployeeData],
+        privacy: &Privacy,
+        options: &MergeOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let header_format = output::header_format();
+        let date_format = output::date_format();
+
+        if let Some(window_days) = options.return_window_days {
+            let today = chrono::Local::now().date_naive();
+            let returns = return_to_work::build_return_list(filtered_data, today, window_days);
+            let exam_count = returns.iter().filter(|entry| entry.badanie_kontrolne).count();
+            self.log_message(format!(
+                "\nPowroty do pracy w ciągu {} dni: {}, w tym z badaniem kontrolnym: {}",
+                window_days,
+                returns.len(),
+                exam_count
+            ));
+            for entry in returns.iter().filter(|entry| entry.badanie_kontrolne) {
+                self.log_message(format!(
+                    "Badanie kontrolne: {} {} (powrót {}, nieobecność {} dni, w tym choroba {} dni)",
+                    entry.nazwisko,
+                    entry.imie,
+                    entry.data_powrotu.format("%d.%m.%Y"),
+                    entry.dni_nieobecnosci,
+                    entry.dni_choroby
+                ));
+            }
+            return_to_work::write_sheet(workbook, &returns, privacy, &header_format, &date_format)?;
+        }
+
+        if let Some((year, month)) = options.attendance_month {
+            attendance::write_sheet(workbook, filtered_data, year, month, privacy, &header_format)?;
+            self.log_message(format!("Dodano listę obecności za {:02}.{}", month, year));
+        }
+
+        if let Some((period_start, period_end)) = options.statistics_period {
+            let stats = statistics::compute(employees, filtered_data, period_start, period_end);
+            self.log_message(format!(
+                "\nWskaźnik absencji {} - {}: {:.2}%",
+                period_start.format("%d.%m.%Y"),
+                period_end.format("%d.%m.%Y"),
+                stats.firma.wskaznik_absencji * 100.0
+            ));
+            statistics::write_sheet(workbook, &stats, privacy, &header_format, &date_format)?;
+            if let Some(json_file) = &options.statistics_json {
+                let mut stats = stats;
+                for employee in &mut stats.pracownicy {
+                    employee.pesel = privacy.pesel(&employee.pesel);
+                    employee.nazwisko = privacy.name(&employee.nazwisko);
+                    employee.imie = privacy.name(&employee.imie);
+                }
+                let json = serde_json::to_string_pretty(&stats)?;
+                let file = self.write_output("statystyki", json_file, json.as_bytes(), options.password.as_deref())?;
+                self.log_message(format!("Zapisano statystyki: {}", file.display()));
+            }
+        }
+
+        let to_verify = compliance::write_sheet(workbook, filtered_data, privacy, &header_format, &date_format)?;
+        if to_verify > 0 {
+            self.log_message(format!(
+                "\nZaświadczenia do weryfikacji w ZUS (wystawione wstecz lub po okresie): {}",
+                to_verify
+            ));
+        }
+
+        if let Some(gap_days) = options.episode_gap_days {
+            let episodes = episodes::build_episodes(filtered_data, gap_days);
+            self.log_message(format!("\nEpizody nieobecności: {} (z {} zaświadczeń)", episodes.len(), filtered_data.len()));
+            episodes::write_sheet(workbook, &episodes, gap_days, privacy, &header_format, &date_format)?;
+        }
+
+        if let Some(criteria) = &options.control_criteria {
+            let candidates = control::find_candidates(filtered_data, chrono::Local::now().date_naive(), criteria);
+            self.log_message(format!("\nKandydaci do kontroli zwolnienia: {}", candidates.len()));
+            control::write_sheet(workbook, &candidates, criteria, privacy, &header_format, &date_format)?;
+        }
+
+        if options.check_anomalies {
+            let found = anomalies::check(filtered_data, chrono::Local::now().date_naive());
+            self.log_message(format!("\nWykryte anomalie w danych: {}", found.len()));
+            for (rule, count) in anomalies::summary(&found) {
+                self.log_message(format!("{} {}: {}", rule.id(), rule.name(), count));
+            }
+            if !found.is_empty() {
+                anomalies::write_sheet(workbook, &found, filtered_data, privacy, &header_format, &date_format)?;
+            }
+        }
+
+        if let Some(source) = &options.changes {
+            // Historia jest odczytywana przed zapisaniem bieżącego uruchomienia
+            let describe = |(entry, certificates): (history::ImportEntry, Vec<EmployeeData>)| {
+                let time = entry.czas.get(..16).unwrap_or(&entry.czas).replace('T', " ");
+                (format!("import nr {} z {} ({})", entry.id, time, entry.plik_l4), certificates)
+            };
+            let previous = match source {
+                ChangesSource::PreviousRun => {
+                    let second_sha256 = self.input_sha256("l4");
+                    let which = PreviousImport::Latest { second_sha256: &second_sha256, period: options.period() };
+                    history::previous_import(which)?.map(describe)
+                }
+                ChangesSource::Import(id) => Some(
+                    history::previous_import(PreviousImport::Id(*id))?
+                        .map(describe)
+                        .ok_or_else(|| format!("Nie ma importu nr {} w historii", id))?,
+                ),
+                ChangesSource::File(path) => {
+                    self.inputs.push(FileHash::new("poprzedni_raport", path, None)?);
+                    Some((path.display().to_string(), changes::read_report(path, &options.layout)?))
+                }
+            };
+            match previous {
+                Some((compared_with, previous)) => {
+                    let found = changes::compare(&previous, filtered_data);
+                    self.log_message(format!("\nZmiany względem: {} - {}", compared_with, found.len()));
+                    for (kind, count) in changes::summary(&found) {
+                        self.log_message(format!("{}: {}", kind.label(), count));
+                    }
+                    changes::write_sheet(workbook, &found, &compared_with, privacy, &header_format, &date_format)?;
+                }
+                None => self.log_message(
+                    "\nBrak w historii importu innego pliku L4 lub za inny okres - pominięto arkusz \"Zmiany\"".to_string(),
+                ),
+            }
+        }
         Ok(())
     }
 
+    // Osobny plik (w formacie pliku wynikowego) dla każdej wartości kolumny podziału
+    // oraz plik indeksu obok pliku wynikowego
+    fn write_split_files(
+        &mut self,
+        output_file: &Path,
+        report: &Report,
+        summaries: &BTreeMap<String, Summary>,
+        split: &SplitOptions,
+        format: OutputFormat,
+        options: &MergeOptions,
+    ) -> Result<()> {
+        let column = split.column.as_str();
+
+        let directory = output_file.parent().unwrap_or(Path::new(""));
+        let today = chrono::Local::now().date_naive();
+        let mut used_names = HashSet::new();
+        let mut files = Vec::new();
+        let writer = output::writer(format, options);
+
+        for (wartosc, rows) in split::group(report.rows, column) {
+            let name = split::file_name(&split.file_name_template, &wartosc, today, format.extension(), &mut used_names);
+            let plik = directory.join(name);
+            // Podsumowanie tylko z danymi grupy - bez liczb całej firmy i innych działów
+            let summary = summaries.get(&wartosc).cloned().unwrap_or_default();
+            let bytes = writer.write(&Report { rows: &rows, unmatched: &[], summary: &summary, ..*report })?;
+            let plik = self.write_output("podzial", &plik, &bytes, options.password.as_deref())?;
+            files.push(SplitFile { wartosc, plik, wiersze: rows.len() });
+        }
+        let index = split::index_workbook(&files, column, &output::header_format())?;
+        let index_file = self.write_output("indeks", &output_file.with_extension("indeks.xlsx"), &index, options.password.as_deref())?;
+
+        self.log_message(format!("\nPodział według kolumny {}, liczba plików: {}", column, files.len()));
+        for file in &files {
+            self.log_message(format!("{} - {} ({} wierszy)", file.wartosc, file.plik.display(), file.wiersze));
+        }
+        self.log_message(format!("Utworzono indeks: {}", index_file.display()));
+        Ok(())
+    }
+
+    pub fn absent_on(
+        &mut self,
+        first_file: &PathBuf,
+        second_file: &PathBuf,
+        first_sheet: &str,
+        second_sheet: &str,
+        date: NaiveDate,
+    ) -> Result<Vec<AbsentEmployee>> {
+        let MatchedData { matched, .. } = self.load_matched_data(first_file, second_file, first_sheet, second_sheet, &[])?;
+        let absent = snapshot::absent_on(&matched, date);
+        self.log_message(format!("\nNieobecni w dniu {}: {}", date.format("%d.%m.%Y"), absent.len()));
+        Ok(absent)
+    }
+
+    // Wczytuje oba pliki i łączy wiersze L4 z danymi z listy pracowników
+    fn load_matched_data(
+        &mut self,
+        first_file: &PathBuf,
+        second_file: &PathBuf,
+        first_sheet: &str,
+        second_sheet: &str,
+        extra_columns: &[String],
+    ) -> Result<MatchedData> {
+        let (columns, data, skipped) = self.prepare_data(first_file, second_file, first_sheet, second_sheet, extra_columns)?;
+        let common_pesels = self.find_common_pesels(&data);
+
+        let (employees, l4): (Vec<EmployeeData>, Vec<EmployeeData>) =
+            data.into_iter().partition(|employee| employee.source == "pracownicy");
+
+        let roster: HashMap<&str, &EmployeeData> = employees
+            .iter()
+            .map(|employee| (employee.pesel.as_str(), employee))
+            .collect();
+
+        let (matched, unmatched): (Vec<EmployeeData>, Vec<EmployeeData>) = l4
+            .into_iter()
+            .partition(|employee| common_pesels.contains(&employee.pesel));
+        let matched = matched
+            .into_iter()
+            .map(|mut employee| {
+                if let Some(pracownik) = roster.get(employee.pesel.as_str()) {
+                    employee.dzial = pracownik.dzial.clone();
+                    employee.data_zatrudnienia = pracownik.data_zatrudnienia;
+                    employee.dodatkowe = pracownik.dodatkowe.clone();
+                }
+                employee
+            })
+            .collect();
+
+        Ok(MatchedData { columns, employees, matched, unmatched, skipped })
+    }
+
     fn prepare_data(
         &mut self,
         first_file: &PathBuf,
         second_file: &PathBuf,
         first_sheet: &str,
         second_sheet: &str,
-    ) -> Result<(Vec<String>, Vec<EmployeeData>)> {
+        extra_columns: &[String],
+    ) -> Result<(Vec<String>, Vec<EmployeeData>, usize)> {
         let range1 = if first_file.extension().and_then(|ext| ext.to_str()).map(|s| s.to_lowercase()) == Some("xlsx".to_string()) {
             let mut workbook: Xlsx<_> = open_workbook(first_file)?;
             workbook.worksheet_range(first_sheet)
@@ -161,23 +909,38 @@ impl ExcelMerger {
                 .ok_or_else(|| anyhow::anyhow!("Nie można otworzyć drugiego arkusza"))??
         };
 
-        let headers = vec![
-            "Nazwisko".to_string(),
-            "Imię".to_string(),
-            "PESEL".to_string(),
-            "Data od".to_string(),
-            "Data do".to_string(),
-            "Na opiekę".to_string(),
-            "Pobyt w szpitalu".to_string(),
-            "Status zaśw.".to_string(),
-        ];
-
+        let mut columns = Vec::new();
         let mut data = Vec::new();
         let mut pracownicy_count = 0;
         let mut l4_count = 0;
+        let mut skipped = 0;
+
+        let roster_header = range1.rows().next();
+        let dzial_col = roster_header.and_then(|header_row| find_column(header_row, &DEPARTMENT_HEADERS));
+        let zatrudnienie_col = roster_header.and_then(|header_row| find_column(header_row, &EMPLOYMENT_DATE_HEADERS));
+        // Kolumny dodatkowe szukane po nagłówku, bez względu na wielkość liter
+        let mut extra_cols = Vec::new();
+        for name in extra_columns {
+            let lowercase = name.trim().to_lowercase();
+            match roster_header.and_then(|header_row| find_column(header_row, &[lowercase.as_str()])) {
+                Some(col) if extra_cols.iter().any(|(_, found)| *found == col) => {}
+                Some(col) => {
+                    let header = roster_header
+                        .and_then(|header_row| header_row.get(col))
+                        .map(|cell| cell.to_string().trim().to_string())
+                        .unwrap_or_else(|| name.trim().to_string());
+                    extra_cols.push((header.clone(), col));
+                    columns.push(header);
+                }
+                None => self.log_message(format!("Nie znaleziono kolumny {} w pliku pracowników", name)),
+            }
+        }
+        let wskazania_col = range2.rows().next().and_then(|header_row| find_column(header_row, &INSTRUCTION_HEADERS));
+        let first_row1 = range1.start().map(|(row, _)| row as usize).unwrap_or(0);
+        let first_row2 = range2.start().map(|(row, _)| row as usize).unwrap_or(0);
 
         self.log_message("\nWczytywanie danych z pliku pracowników:".to_string());
-        for row in range1.rows().skip(1) {
+        for (idx, row) in range1.rows().enumerate().skip(1) {
             if let (Some(DataType::String(nazwisko)), Some(DataType::String(imie)), Some(DataType::String(pesel))) = 
                 (row.get(0), row.get(1), row.get(2)) {
                 
@@ -191,14 +954,31 @@ impl ExcelMerger {
                     pobyt_w_szpitalu: String::new(),
                     status: String::new(),
                     source: "pracownicy".to_string(),
+                    dzial: dzial_col
+                        .and_then(|col| row.get(col))
+                        .map(|cell| cell.to_string().trim().to_string())
+                        .unwrap_or_default(),
+                    nr_zaswiadczenia: String::new(),
+                    data_wystawienia: None,
+                    data_zatrudnienia: zatrudnienie_col
+                        .and_then(|col| row.get(col))
+                        .and_then(Self::cell_to_date),
+                    wskazania: String::new(),
+                    wiersz: first_row1 + idx + 1,
+                    dodatkowe: extra_cols
+                        .iter()
+                        .map(|(name, col)| (name.clone(), row.get(*col).map(cell_to_text).unwrap_or_default()))
+                        .collect(),
                 });
                 pracownicy_count += 1;
+            } else if !is_empty_row(row) {
+                skipped += 1;
             }
         }
         self.log_message(format!("Wczytano {} PESEL-i", pracownicy_count));
 
         self.log_message("\nWczytywanie danych z pliku L4:".to_string());
-        for row in range2.rows().skip(1) {
+        for (idx, row) in range2.rows().enumerate().skip(1) {
             if let Some(DataType::String(ubezpieczony)) = row.get(0).map(|c| c.to_owned()) {
                 let pesel_regex = Regex::new(r".*\s(\d{11})$")?;
                 if let Some(captures) = pesel_regex.captures(&ubezpieczony) {
@@ -207,9 +987,11 @@ impl ExcelMerger {
                     let nazwisko = parts.first().unwrap_or(&"").to_string();
                     let imie = parts.get(1).unwrap_or(&"").to_string();
 
+                    let data_wyst_str = row.get(2).and_then(|c| c.get_string()).unwrap_or_default().to_string();
                     let data_od_str = row.get(3).and_then(|c| c.get_string()).unwrap_or_default().to_string();
                     let data_do_str = row.get(4).and_then(|c| c.get_string()).unwrap_or_default().to_string();
 
+                    let data_wystawienia = Self::parse_date(&data_wyst_str);
                     let data_od = Self::parse_date(&data_od_str);
                     let data_do = Self::parse_date(&data_do_str);
 
@@ -223,14 +1005,31 @@ impl ExcelMerger {
                         pobyt_w_szpitalu: row.get(6).and_then(|c| c.get_string()).unwrap_or_default().to_string(),
                         status: row.get(7).and_then(|c| c.get_string()).unwrap_or_default().to_string(),
                         source: "l4".to_string(),
+                        dzial: String::new(),
+                        nr_zaswiadczenia: row.get(1).map(|c| c.to_string().trim().to_string()).unwrap_or_default(),
+                        data_wystawienia,
+                        data_zatrudnienia: None,
+                        wskazania: wskazania_col
+                            .and_then(|col| row.get(col))
+                            .map(|cell| cell.to_string().trim().to_string())
+                            .unwrap_or_default(),
+                        wiersz: first_row2 + idx + 1,
+                        dodatkowe: Vec::new(),
                     });
                     l4_count += 1;
+                } else {
+                    skipped += 1;
                 }
+            } else if !is_empty_row(row) {
+                skipped += 1;
             }
         }
         self.log_message(format!("Wczytano {} PESEL-i", l4_count));
+        if skipped > 0 {
+            self.log_message(format!("Pominięto wierszy, których nie udało się odczytać: {}", skipped));
+        }
 
-        Ok((headers, data))
+        Ok((columns, data, skipped))
     }
 
     fn find_common_pesels(&mut self, data: &[EmployeeData]) -> HashSet<String> {
@@ -257,50 +1056,4 @@ impl ExcelMerger {
 
         common_pesels
     }
-
-    fn write_headers(&self, sheet: &mut Worksheet, headers: &Vec<String>, header_format: &Format) -> Result<()> {
-        for (col, header) in headers.iter().enumerate() {
-            sheet.write_string_with_format(0, col as u16, &header.to_string(), header_format)?;
-        }
-        Ok(())
-    }
-
-    fn write_data(&mut self, sheet: &mut Worksheet, data: &Vec<EmployeeData>, date_format: &Format) -> Result<()> {
-        sheet.set_column_width(0, 20.0)?; // Nazwisko
-        sheet.set_column_width(1, 15.0)?; // Imię
-        sheet.set_column_width(2, 12.0)?; // PESEL
-        sheet.set_column_width(3, 12.0)?; // Data od
-        sheet.set_column_width(4, 12.0)?; // Data do
-        sheet.set_column_width(5, 10.0)?; // Na opiekę
-        sheet.set_column_width(6, 15.0)?; // Pobyt w szpitalu
-        sheet.set_column_width(7, 12.0)?; // Status zaśw.
-
-        let mut row = 1;
-        for employee in data {
-            if employee.source == "l4" {
-                sheet.write_string(row, 0, &employee.nazwisko)?;
-                sheet.write_string(row, 1, &employee.imie)?;
-                sheet.write_string(row, 2, &employee.pesel)?;
-                
-                if let Some(excel_date) = employee.data_od {
-                    sheet.write_number_with_format(row, 3, excel_date, date_format)?;
-                } else {
-                    sheet.write_blank(row, 3, date_format)?;
-                }
-                
-                if let Some(excel_date) = employee.data_do {
-                    sheet.write_number_with_format(row, 4, excel_date, date_format)?;
-                } else {
-                    sheet.write_blank(row, 4, date_format)?;
-                }
-                
-                sheet.write_string(row, 5, &employee.na_opieke)?;
-                sheet.write_string(row, 6, &employee.pobyt_w_szpitalu)?;
-                sheet.write_string(row, 7, &employee.status)?;
-                
-                row += 1;
-            }
-        }
-        Ok(())
-    }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,395 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::excel_merger::{date_to_excel, excel_to_date, EmployeeData};
+
+// Baza historii w katalogu ustawień. Jest prowadzona tylko na żądanie i zawiera numery PESEL i nazwiska
+// bez względu na tryb danych osobowych raportu, dlatego nie zapisuje się w niej wskazań lekarskich
+// ani pobytu w szpitalu - tylko to, czego wymagają porównania i limity roczne.
+const DATABASE_FILE: &str = "historia.sqlite";
+
+// Zaświadczenia są identyfikowane numerem i numerem PESEL. Tabela "zaswiadczenia" zawiera
+// ostatni stan każdego zaświadczenia, a "wystapienia" - stan w każdym imporcie.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS importy (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    czas TEXT NOT NULL,
+    wersja TEXT NOT NULL,
+    plik_pracownikow TEXT NOT NULL,
+    arkusz_pracownikow TEXT NOT NULL,
+    plik_l4 TEXT NOT NULL,
+    arkusz_l4 TEXT NOT NULL,
+    sha256_l4 TEXT NOT NULL,
+    okres_od TEXT NOT NULL,
+    okres_do TEXT NOT NULL,
+    pracownicy INTEGER NOT NULL,
+    zaswiadczenia INTEGER NOT NULL,
+    dopasowane INTEGER NOT NULL,
+    nowe INTEGER NOT NULL,
+    zmienione INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS zaswiadczenia (
+    nr_zaswiadczenia TEXT NOT NULL,
+    pesel TEXT NOT NULL,
+    nazwisko TEXT NOT NULL,
+    imie TEXT NOT NULL,
+    dzial TEXT NOT NULL,
+    data_wystawienia TEXT,
+    data_od TEXT,
+    data_do TEXT,
+    opieka INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    dopasowane INTEGER NOT NULL,
+    pierwszy_import INTEGER NOT NULL REFERENCES importy(id),
+    ostatni_import INTEGER NOT NULL REFERENCES importy(id),
+    PRIMARY KEY (nr_zaswiadczenia, pesel)
+);
+CREATE TABLE IF NOT EXISTS wystapienia (
+    import_id INTEGER NOT NULL REFERENCES importy(id),
+    nr_zaswiadczenia TEXT NOT NULL,
+    pesel TEXT NOT NULL,
+    status TEXT NOT NULL,
+    data_od TEXT,
+    data_do TEXT,
+    dopasowane INTEGER NOT NULL,
+    PRIMARY KEY (import_id, nr_zaswiadczenia, pesel)
+);
+CREATE VIEW IF NOT EXISTS historia_importow AS
+    SELECT id, czas, plik_l4, okres_od, okres_do, zaswiadczenia, dopasowane, nowe, zmienione
+    FROM importy ORDER BY id DESC;
+";
+
+pub fn database_file() -> PathBuf {
+    config::config_dir().join(DATABASE_FILE)
+}
+
+fn open() -> Result<Connection> {
+    let dir = config::config_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("Nie można utworzyć katalogu {}", dir.display()))?;
+    let path = database_file();
+    let connection = Connection::open(&path)
+        .with_context(|| format!("Nie można otworzyć bazy historii {}", path.display()))?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+fn date_text(date: Option<f64>) -> Option<String> {
+    date.and_then(excel_to_date).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn text_date(text: Option<String>) -> Option<f64> {
+    text.and_then(|text| NaiveDate::parse_from_str(&text, "%Y-%m-%d").ok())
+        .map(date_to_excel)
+}
+
+// Dane jednego uruchomienia zapisywane w historii
+pub struct Import<'a> {
+    pub started: DateTime<Local>,
+    pub first_file: &'a Path,
+    pub first_sheet: &'a str,
+    pub second_file: &'a Path,
+    pub second_sheet: &'a str,
+    // Suma SHA-256 pliku L4 - do rozpoznania ponownego przetworzenia tych samych danych
+    pub second_sha256: &'a str,
+    pub period: (NaiveDate, NaiveDate),
+    pub employees: usize,
+    // Zaświadczenia osób z listy pracowników (z działem z listy)
+    pub matched: &'a [EmployeeData],
+    // Zaświadczenia osób spoza listy pracowników
+    pub unmatched: &'a [EmployeeData],
+}
+
+pub struct ImportResult {
+    pub id: i64,
+    pub nowe: usize,
+    pub zmienione: usize,
+    // Zaświadczenia bez numeru nie mogą być rozpoznane w kolejnych importach i nie są zapisywane
+    pub bez_numeru: usize,
+}
+
+pub fn record(import: &Import) -> Result<ImportResult> {
+    let mut connection = open()?;
+    let transaction = connection.transaction()?;
+
+    let certificates: Vec<(&EmployeeData, bool)> = import
+        .matched
+        .iter()
+        .map(|employee| (employee, true))
+        .chain(import.unmatched.iter().map(|employee| (employee, false)))
+        .collect();
+    let bez_numeru = certificates
+        .iter()
+        .filter(|(employee, _)| employee.nr_zaswiadczenia.trim().is_empty())
+        .count();
+
+    transaction.execute(
+        "INSERT INTO importy (czas, wersja, plik_pracownikow, arkusz_pracownikow, plik_l4, arkusz_l4, sha256_l4,
+            okres_od, okres_do, pracownicy, zaswiadczenia, dopasowane, nowe, zmienione)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, 0)",
+        params![
+            import.started.to_rfc3339(),
+            env!("CARGO_PKG_VERSION"),
+            import.first_file.display().to_string(),
+            import.first_sheet,
+            import.second_file.display().to_string(),
+            import.second_sheet,
+            import.second_sha256,
+            import.period.0.format("%Y-%m-%d").to_string(),
+            import.period.1.format("%Y-%m-%d").to_string(),
+            import.employees as i64,
+            (certificates.len() - bez_numeru) as i64,
+            import.matched.len() as i64,
+        ],
+    )?;
+    let id = transaction.last_insert_rowid();
+
+    let mut nowe = 0;
+    let mut zmienione = 0;
+    for (employee, dopasowane) in certificates {
+        let nr = employee.nr_zaswiadczenia.trim();
+        if nr.is_empty() {
+            continue;
+        }
+        let data_od = date_text(employee.data_od);
+        let data_do = date_text(employee.data_do);
+
+        let previous: Option<(String, Option<String>, Option<String>)> = transaction
+            .query_row(
+                "SELECT status, data_od, data_do FROM zaswiadczenia WHERE nr_zaswiadczenia = ?1 AND pesel = ?2",
+                params![nr, employee.pesel],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        match &previous {
+            None => nowe += 1,
+            Some(previous) if *previous != (employee.status.clone(), data_od.clone(), data_do.clone()) => zmienione += 1,
+            Some(_) => {}
+        }
+
+        transaction.execute(
+            "INSERT INTO zaswiadczenia (nr_zaswiadczenia, pesel, nazwisko, imie, dzial, data_wystawienia, data_od,
+                data_do, opieka, status, dopasowane, pierwszy_import, ostatni_import)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?12)
+             ON CONFLICT (nr_zaswiadczenia, pesel) DO UPDATE SET
+                nazwisko = excluded.nazwisko, imie = excluded.imie, dzial = excluded.dzial,
+                data_wystawienia = excluded.data_wystawienia, data_od = excluded.data_od, data_do = excluded.data_do,
+                opieka = excluded.opieka, status = excluded.status, dopasowane = excluded.dopasowane,
+                ostatni_import = excluded.ostatni_import",
+            params![
+                nr,
+                employee.pesel,
+                employee.nazwisko,
+                employee.imie,
+                employee.dzial,
+                date_text(employee.data_wystawienia),
+                data_od,
+                data_do,
+                employee.is_care(),
+                employee.status,
+                dopasowane,
+                id,
+            ],
+        )?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO wystapienia (import_id, nr_zaswiadczenia, pesel, status, data_od, data_do, dopasowane)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, nr, employee.pesel, employee.status, data_od, data_do, dopasowane],
+        )?;
+    }
+
+    transaction.execute(
+        "UPDATE importy SET nowe = ?1, zmienione = ?2 WHERE id = ?3",
+        params![nowe as i64, zmienione as i64, id],
+    )?;
+    transaction.commit()?;
+    Ok(ImportResult { id, nowe, zmienione, bez_numeru })
+}
+
+pub struct ImportEntry {
+    pub id: i64,
+    pub czas: String,
+    pub plik_l4: String,
+    pub okres_od: String,
+    pub okres_do: String,
+    pub zaswiadczenia: i64,
+    pub dopasowane: i64,
+    pub nowe: i64,
+    pub zmienione: i64,
+}
+
+const IMPORT_COLUMNS: &str = "id, czas, plik_l4, okres_od, okres_do, zaswiadczenia, dopasowane, nowe, zmienione";
+
+fn import_entry(row: &rusqlite::Row) -> rusqlite::Result<ImportEntry> {
+    Ok(ImportEntry {
+        id: row.get(0)?,
+        czas: row.get(1)?,
+        plik_l4: row.get(2)?,
+        okres_od: row.get(3)?,
+        okres_do: row.get(4)?,
+        zaswiadczenia: row.get(5)?,
+        dopasowane: row.get(6)?,
+        nowe: row.get(7)?,
+        zmienione: row.get(8)?,
+    })
+}
+
+// Historia importów od najnowszego; brak bazy oznacza pustą historię
+pub fn imports() -> Result<Vec<ImportEntry>> {
+    if !database_file().exists() {
+        return Ok(Vec::new());
+    }
+    let connection = open()?;
+    let mut statement = connection.prepare(&format!("SELECT {} FROM historia_importow", IMPORT_COLUMNS))?;
+    let entries = statement.query_map([], import_entry)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+// Zaświadczenie z wiersza zapytania; kolumny w kolejności jak w `certificates`
+fn certificate(row: &rusqlite::Row) -> rusqlite::Result<EmployeeData> {
+    Ok(EmployeeData {
+        nr_zaswiadczenia: row.get(0)?,
+        pesel: row.get(1)?,
+        nazwisko: row.get(2)?,
+        imie: row.get(3)?,
+        dzial: row.get(4)?,
+        data_wystawienia: text_date(row.get(5)?),
+        data_od: text_date(row.get(6)?),
+        data_do: text_date(row.get(7)?),
+        na_opieke: if row.get(8)? { "tak" } else { "nie" }.to_string(),
+        pobyt_w_szpitalu: String::new(),
+        status: row.get(9)?,
+        wskazania: String::new(),
+        source: "historia".to_string(),
+        data_zatrudnienia: None,
+        wiersz: 0,
+        dodatkowe: Vec::new(),
+    })
+}
+
+// Zaświadczenia osób z listy pracowników (w ostatnim znanym stanie), których okres zachodzi na podany
+pub fn certificates(start: NaiveDate, end: NaiveDate) -> Result<Vec<EmployeeData>> {
+    if !database_file().exists() {
+        return Ok(Vec::new());
+    }
+    let connection = open()?;
+    let mut statement = connection.prepare(
+        "SELECT nr_zaswiadczenia, pesel, nazwisko, imie, dzial, data_wystawienia, data_od, data_do,
+            opieka, status
+         FROM zaswiadczenia
+         WHERE dopasowane = 1 AND data_od <= ?2 AND data_do >= ?1
+         ORDER BY nazwisko, pesel, data_od",
+    )?;
+    let certificates = statement
+        .query_map(
+            params![start.format("%Y-%m-%d").to_string(), end.format("%Y-%m-%d").to_string()],
+            certificate,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(certificates)
+}
+
+// Import, z którym porównywane są bieżące dane
+pub enum PreviousImport<'a> {
+    // Najnowszy import innego pliku L4 (według sumy SHA-256) albo za inny okres - ponowne
+    // przetworzenie tych samych danych nie zasłania poprzedniego miesiąca
+    Latest { second_sha256: &'a str, period: (NaiveDate, NaiveDate) },
+    // Import wskazany numerem z historii importów
+    Id(i64),
+}
+
+// Wybrany import z zaświadczeniami osób z listy pracowników w stanie z tego importu
+pub fn previous_import(which: PreviousImport) -> Result<Option<(ImportEntry, Vec<EmployeeData>)>> {
+    if !database_file().exists() {
+        return Ok(None);
+    }
+    let connection = open()?;
+    let entry = match which {
+        PreviousImport::Latest { second_sha256, period } => connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM importy WHERE sha256_l4 <> ?1 OR okres_od <> ?2 OR okres_do <> ?3
+                     ORDER BY id DESC LIMIT 1",
+                    IMPORT_COLUMNS
+                ),
+                params![
+                    second_sha256,
+                    period.0.format("%Y-%m-%d").to_string(),
+                    period.1.format("%Y-%m-%d").to_string()
+                ],
+                import_entry,
+            )
+            .optional()?,
+        PreviousImport::Id(id) => connection
+            .query_row(&format!("SELECT {} FROM importy WHERE id = ?1", IMPORT_COLUMNS), params![id], import_entry)
+            .optional()?,
+    };
+    let Some(entry) = entry else {
+        return Ok(None);
+    };
+    let mut statement = connection.prepare(
+        "SELECT w.nr_zaswiadczenia, w.pesel, z.nazwisko, z.imie, z.dzial, z.data_wystawienia, w.data_od, w.data_do,
+            z.opieka, w.status
+         FROM wystapienia w JOIN zaswiadczenia z USING (nr_zaswiadczenia, pesel)
+         WHERE w.import_id = ?1 AND w.dopasowane = 1",
+    )?;
+    let certificates = statement
+        .query_map(params![entry.id], certificate)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Some((entry, certificates)))
+}
+
+pub struct YearTotal {
+    pub pesel: String,
+    pub nazwisko: String,
+    pub imie: String,
+    pub dzial: String,
+    // Dni zwolnień z powodu własnej choroby (łącznie z pobytem w szpitalu) przypadające na rok
+    pub dni_choroby: usize,
+    // Dni zwolnień na opiekę przypadające na rok
+    pub dni_opieki: usize,
+    pub zaswiadczenia: usize,
+}
+
+// Dni zwolnień każdej osoby w roku kalendarzowym ze wszystkich importów - do pilnowania limitów rocznych.
+// Dzień objęty kilkoma zaświadczeniami jest liczony raz; anulowane zaświadczenia są pomijane.
+pub fn year_totals(year: i32) -> Result<Vec<YearTotal>> {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1).context("Niepoprawny rok")?;
+    let end = NaiveDate::from_ymd_opt(year, 12, 31).context("Niepoprawny rok")?;
+
+    let mut people: HashMap<String, (YearTotal, BTreeSet<NaiveDate>, BTreeSet<NaiveDate>)> = HashMap::new();
+    for employee in certificates(start, end)?.iter().filter(|employee| !employee.is_cancelled()) {
+        let Some((from, to)) = employee.okres() else {
+            continue;
+        };
+        let (total, illness, care) = people.entry(employee.pesel.clone()).or_insert_with(|| {
+            let total = YearTotal {
+                pesel: employee.pesel.clone(),
+                nazwisko: employee.nazwisko.clone(),
+                imie: employee.imie.clone(),
+                dzial: employee.dzial.clone(),
+                dni_choroby: 0,
+                dni_opieki: 0,
+                zaswiadczenia: 0,
+            };
+            (total, BTreeSet::new(), BTreeSet::new())
+        });
+        total.zaswiadczenia += 1;
+        let days = if employee.is_care() { care } else { illness };
+        days.extend(from.max(start).iter_days().take_while(|date| *date <= to.min(end)));
+    }
+
+    let mut totals: Vec<YearTotal> = people
+        .into_values()
+        .map(|(mut total, illness, care)| {
+            total.dni_choroby = illness.len();
+            total.dni_opieki = care.len();
+            total
+        })
+        .collect();
+    totals.sort_by(|a, b| a.nazwisko.cmp(&b.nazwisko).then_with(|| a.pesel.cmp(&b.pesel)));
+    Ok(totals)
+}
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use chrono::Local;
+use std::fmt::Write;
+
+use crate::excel_merger::{excel_to_date, is_yes, EmployeeData};
+use crate::layout::{self, Field};
+use crate::output::{Report, ReportWriter};
+
+// Jeden plik bez zewnętrznych zasobów - style i skrypt sortowania są wpisane w dokument
+pub struct HtmlWriter {
+    // Nazwa firmy w nagłówku raportu (pusta - pomijana)
+    pub company: String,
+}
+
+const STYLE: &str = "
+body { font-family: 'Segoe UI', Arial, sans-serif; margin: 24px; color: #2c3e50; }
+h1 { font-size: 22px; margin-bottom: 4px; }
+h2 { font-size: 17px; margin-top: 28px; }
+.info { color: #607d8b; margin: 2px 0; }
+.summary { display: flex; flex-wrap: wrap; gap: 24px; align-items: flex-start; }
+table { border-collapse: collapse; font-size: 13px; }
+th, td { border: 1px solid #d0d7de; padding: 4px 8px; text-align: left; }
+th { background: #4f81bd; color: #fff; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th.asc::after { content: ' \\25B2'; }
+table.sortable th.desc::after { content: ' \\25BC'; }
+tbody tr:nth-child(even) { background: #f6f8fa; }
+td.number { text-align: right; }
+tr.ongoing td { background: #c6efce; }
+tr.hospital td { background: #ddebf7; }
+tr.cancelled td { color: #808080; text-decoration: line-through; }
+.legend span { display: inline-block; padding: 2px 8px; margin-right: 8px; font-size: 12px; }
+@media print { table.sortable th::after { content: ''; } }
+";
+
+// Sortowanie po kliknięciu nagłówka; wartości z atrybutu data-sort (daty RRRR-MM-DD, liczby)
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(function (table) {
+  table.querySelectorAll('th').forEach(function (th, index) {
+    th.addEventListener('click', function () {
+      var ascending = !th.classList.contains('asc');
+      table.querySelectorAll('th').forEach(function (other) { other.classList.remove('asc', 'desc'); });
+      th.classList.add(ascending ? 'asc' : 'desc');
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      var value = function (row) {
+        var cell = row.cells[index];
+        return cell.hasAttribute('data-sort') ? cell.getAttribute('data-sort') : cell.textContent.trim();
+      };
+      rows.sort(function (a, b) {
+        var x = value(a), y = value(b);
+        var result = (x !== '' && y !== '' && !isNaN(x) && !isNaN(y)) ? x - y : x.localeCompare(y, 'pl');
+        return ascending ? result : -result;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Komórka z wartością do sortowania dla dat i liczb
+fn cell(employee: &EmployeeData, field: &Field) -> String {
+    let text = escape(&layout::text_value(employee, field));
+    match field {
+        Field::DataWystawienia | Field::DataOd | Field::DataDo => {
+            let sort = layout::date_value(employee, field)
+                .and_then(excel_to_date)
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            format!("<td data-sort=\"{}\">{}</td>", sort, text)
+        }
+        Field::Dni => format!("<td class=\"number\">{}</td>", text),
+        _ => format!("<td>{}</td>", text),
+    }
+}
+
+// Klasa wiersza zgodna z wyróżnieniami w arkuszu "Dane"
+fn row_class(employee: &EmployeeData) -> &'static str {
+    let today = Local::now().date_naive();
+    if employee.is_cancelled() {
+        "cancelled"
+    } else if is_yes(&employee.pobyt_w_szpitalu) {
+        "hospital"
+    } else if employee.okres().is_some_and(|(start, end)| start <= today && today <= end) {
+        "ongoing"
+    } else {
+        ""
+    }
+}
+
+fn counts_table(html: &mut String, headers: &[&str], rows: &[(String, Vec<String>)]) -> std::fmt::Result {
+    html.push_str("<table><thead><tr>");
+    for header in headers {
+        write!(html, "<th>{}</th>", escape(header))?;
+    }
+    html.push_str("</tr></thead><tbody>");
+    for (label, values) in rows {
+        write!(html, "<tr><td>{}</td>", escape(label))?;
+        for value in values {
+            write!(html, "<td class=\"number\">{}</td>", value)?;
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>\n");
+    Ok(())
+}
+
+impl ReportWriter for HtmlWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let summary = report.summary;
+        let mut html = String::new();
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"pl\">\n<head>\n<meta charset=\"utf-8\">\n<title>Raport L4</title>\n<style>{}</style>\n</head>\n<body>",
+            STYLE
+        )?;
+
+        html.push_str("<h1>Raport zwolnień lekarskich (L4)</h1>\n");
+        if !self.company.trim().is_empty() {
+            writeln!(html, "<p class=\"info\"><strong>{}</strong></p>", escape(self.company.trim()))?;
+        }
+        writeln!(
+            html,
+            "<p class=\"info\">Okres: {} - {}</p>\n<p class=\"info\">Data wygenerowania: {}</p>\n<p class=\"info\">Układ: {}</p>\n<p class=\"info\">Dane osobowe: {}</p>",
+            report.period.0.format("%d.%m.%Y"),
+            report.period.1.format("%d.%m.%Y"),
+            report.started.format("%d.%m.%Y %H:%M"),
+            escape(&report.layout.nazwa),
+            report.privacy.label()
+        )?;
+
+        html.push_str("<h2>Podsumowanie</h2>\n<div class=\"summary\">\n");
+        let counts = [
+            ("Pracownicy na liście", summary.pracownicy),
+            ("Wiersze L4", summary.wiersze_l4),
+            ("Dopasowane zaświadczenia", summary.dopasowane),
+            ("Osoby z zaświadczeniami", summary.osoby_dopasowane),
+            ("Niedopasowane wiersze L4", summary.niedopasowane),
+            ("Pominięte wiersze", summary.pominiete),
+        ];
+        let counts: Vec<(String, Vec<String>)> = counts
+            .iter()
+            .map(|(label, count)| (label.to_string(), vec![count.to_string()]))
+            .collect();
+        counts_table(&mut html, &["", "Liczba"], &counts)?;
+        let statuses: Vec<(String, Vec<String>)> = summary
+            .statusy
+            .iter()
+            .map(|(status, count)| (status.clone(), vec![count.to_string()]))
+            .collect();
+        counts_table(&mut html, &["Status zaśw.", "Zaświadczenia"], &statuses)?;
+        let months: Vec<(String, Vec<String>)> = summary
+            .miesiace
+            .iter()
+            .map(|((year, month), (count, days))| (format!("{}-{:02}", year, month), vec![count.to_string(), days.to_string()]))
+            .collect();
+        counts_table(&mut html, &["Miesiąc", "Zaświadczenia", "Dni zwolnień"], &months)?;
+        let departments: Vec<(String, Vec<String>)> = summary
+            .dzialy
+            .iter()
+            .map(|(name, (count, days))| (name.clone(), vec![count.to_string(), days.to_string()]))
+            .collect();
+        counts_table(&mut html, &["Dział", "Zaświadczenia", "Dni zwolnień"], &departments)?;
+        html.push_str("</div>\n");
+
+        writeln!(html, "<h2>Zaświadczenia ({})</h2>", report.rows.len())?;
+        html.push_str(
+            "<p class=\"legend\"><span style=\"background:#c6efce\">trwające zwolnienie</span>\
+             <span style=\"background:#ddebf7\">pobyt w szpitalu</span>\
+             <span style=\"color:#808080;text-decoration:line-through\">anulowane</span> \
+             Kliknij nagłówek kolumny, aby posortować.</p>\n",
+        );
+        html.push_str("<table class=\"sortable\"><thead><tr>");
+        for header in report.layout.headers() {
+            write!(html, "<th>{}</th>", escape(&header))?;
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        for employee in report.rows {
+            write!(html, "<tr class=\"{}\">", row_class(employee))?;
+            for column in &report.layout.kolumny {
+                html.push_str(&cell(employee, &column.pole));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</tbody></table>\n");
+
+        writeln!(html, "<h2>Wiersze L4 osób spoza listy pracowników ({})</h2>", report.unmatched.len())?;
+        if report.unmatched.is_empty() {
+            html.push_str("<p class=\"info\">Wszystkie wiersze L4 dopasowano do listy pracowników.</p>\n");
+        } else {
+            let fields = [
+                Field::Nazwisko,
+                Field::Imie,
+                Field::Pesel,
+                Field::NrZaswiadczenia,
+                Field::DataOd,
+                Field::DataDo,
+                Field::Status,
+            ];
+            html.push_str("<table class=\"sortable\"><thead><tr><th>Wiersz</th>");
+            for field in &fields {
+                write!(html, "<th>{}</th>", escape(field.label()))?;
+            }
+            html.push_str("</tr></thead>\n<tbody>\n");
+            for employee in report.unmatched {
+                write!(html, "<tr><td class=\"number\">{}</td>", employee.wiersz)?;
+                for field in &fields {
+                    html.push_str(&cell(employee, field));
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody></table>\n");
+        }
+
+        writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT)?;
+        Ok(html.into_bytes())
+    }
+}
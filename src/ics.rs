@@ -0,0 +1,211 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::episodes::{self, Episode};
+use crate::excel_merger::EmployeeData;
+use crate::layout::{self, Field};
+use crate::output::{Report, ReportWriter};
+use crate::privacy::{Privacy, PrivacyMode};
+
+pub const DEFAULT_SUMMARY: &str = "{imie} {nazwisko} – nieobecność";
+
+// Pola z danymi o stanie zdrowia - puste w tytule i pominięte w opisie, o ile nie włączono szczegółów
+const MEDICAL_FIELDS: [Field; 3] = [Field::NaOpieke, Field::PobytWSzpitalu, Field::Wskazania];
+
+#[derive(Debug, Clone)]
+pub struct IcsOptions {
+    // Tytuł wydarzenia ze znacznikami pól, np. {imie}, {nazwisko}, {dzial}, {nr_zaswiadczenia}
+    // albo nagłówki kolumn z listy pracowników, np. {Stanowisko}
+    pub summary: String,
+    // Dane o stanie zdrowia (opieka, szpital, wskazania lekarskie) w opisie wydarzenia
+    pub details: bool,
+}
+
+impl Default for IcsOptions {
+    fn default() -> Self {
+        Self { summary: DEFAULT_SUMMARY.to_string(), details: false }
+    }
+}
+
+// Wydarzenie całodniowe dla każdego nieanulowanego zaświadczenia albo - przy podanej
+// przerwie - dla każdego epizodu nieobecności
+pub struct IcsWriter {
+    pub options: IcsOptions,
+    pub episode_gap_days: Option<u32>,
+}
+
+struct Event<'a> {
+    employee: &'a EmployeeData,
+    start: NaiveDate,
+    end: NaiveDate,
+    zaswiadczenia: Vec<&'a EmployeeData>,
+}
+
+impl IcsWriter {
+    fn summary(&self, employee: &EmployeeData) -> String {
+        let mut summary = self.options.summary.clone();
+        for field in Field::BUILT_IN {
+            let placeholder = format!("{{{}}}", field.key());
+            if summary.contains(&placeholder) {
+                let value = if !self.options.details && MEDICAL_FIELDS.contains(&field) {
+                    String::new()
+                } else {
+                    layout::text_value(employee, &field)
+                };
+                summary = summary.replace(&placeholder, &value);
+            }
+        }
+        for (header, value) in &employee.dodatkowe {
+            summary = summary.replace(&format!("{{{}}}", header), value);
+        }
+        summary.trim().to_string()
+    }
+
+    fn description(&self, event: &Event) -> String {
+        let mut lines = Vec::new();
+        for employee in &event.zaswiadczenia {
+            let mut line = format!(
+                "{} {}: {} - {}",
+                Field::NrZaswiadczenia.label(),
+                employee.nr_zaswiadczenia,
+                layout::text_value(employee, &Field::DataOd),
+                layout::text_value(employee, &Field::DataDo)
+            );
+            if self.options.details {
+                for field in &MEDICAL_FIELDS {
+                    let value = layout::text_value(employee, field);
+                    if !value.trim().is_empty() {
+                        line.push_str(&format!(", {}: {}", field.label(), value.trim()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+// Znaki specjalne wartości tekstowych według RFC 5545
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Linie dłuższe niż 75 bajtów są zawijane, kontynuacja zaczyna się spacją
+fn push_line(text: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        let size = c.len_utf8();
+        if length + size > 75 {
+            text.push_str("\r\n ");
+            length = 1;
+        }
+        text.push(c);
+        length += size;
+    }
+    text.push_str("\r\n");
+}
+
+impl ReportWriter for IcsWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let events: Vec<Event> = match self.episode_gap_days {
+            Some(gap) => episodes::build_episodes(report.rows, gap)
+                .into_iter()
+                .map(|Episode { employee, start, end, zaswiadczenia }| Event { employee, start, end, zaswiadczenia })
+                .collect(),
+            None => report
+                .rows
+                .iter()
+                .filter(|employee| !employee.is_cancelled())
+                .filter_map(|employee| {
+                    let (start, end) = employee.okres().filter(|(start, end)| start <= end)?;
+                    Some(Event { employee, start, end, zaswiadczenia: vec![employee] })
+                })
+                .collect(),
+        };
+
+        let stamp = report.started.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string();
+        let mut text = String::new();
+        push_line(&mut text, "BEGIN:VCALENDAR");
+        push_line(&mut text, "VERSION:2.0");
+        push_line(&mut text, &format!("PRODID:-//l4-filter//{}//PL", env!("CARGO_PKG_VERSION")));
+        push_line(&mut text, "CALSCALE:GREGORIAN");
+        push_line(&mut text, "METHOD:PUBLISH");
+        // Klucz pseudonimów jest potrzebny tylko dla zaświadczeń bez numeru
+        let mut pseudonyms: Option<Privacy> = None;
+        for event in &events {
+            // Identyfikator bez numeru PESEL - numer zaświadczenia i data początku są stałe między eksportami.
+            // Bez numeru zaświadczenia osobę identyfikuje pseudonim z klucza (jak w trybie "pseudonim").
+            let number = event.employee.nr_zaswiadczenia.trim();
+            let id = if number.is_empty() {
+                let privacy = match &mut pseudonyms {
+                    Some(privacy) => privacy,
+                    None => pseudonyms.insert(Privacy::new(PrivacyMode::Pseudonim)?),
+                };
+                let employee = event.employee;
+                privacy.pseudonym(&format!("{}|{}|{}", employee.pesel.trim(), employee.nazwisko.trim(), employee.imie.trim()))
+            } else {
+                number.to_string()
+            };
+            let uid = format!("{}-{}@l4-filter", id, event.start.format("%Y%m%d"));
+            push_line(&mut text, "BEGIN:VEVENT");
+            push_line(&mut text, &format!("UID:{}", escape(&uid)));
+            push_line(&mut text, &format!("DTSTAMP:{}", stamp));
+            push_line(&mut text, &format!("DTSTART;VALUE=DATE:{}", event.start.format("%Y%m%d")));
+            // Koniec wydarzenia całodniowego jest dniem po ostatnim dniu nieobecności
+            push_line(&mut text, &format!("DTEND;VALUE=DATE:{}", (event.end + Duration::days(1)).format("%Y%m%d")));
+            push_line(&mut text, &format!("SUMMARY:{}", escape(&self.summary(event.employee))));
+            push_line(&mut text, &format!("DESCRIPTION:{}", escape(&self.description(event))));
+            push_line(&mut text, "TRANSP:TRANSPARENT");
+            push_line(&mut text, "END:VEVENT");
+        }
+        push_line(&mut text, "END:VCALENDAR");
+        Ok(text.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("a;b,c\\d"), "a\\;b\\,c\\\\d");
+        assert_eq!(escape("linia 1\r\nlinia 2\nlinia 3"), "linia 1\\nlinia 2\\nlinia 3");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        let mut text = String::new();
+        push_line(&mut text, &"a".repeat(75));
+        assert_eq!(text, format!("{}\r\n", "a".repeat(75)));
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_bytes() {
+        let mut text = String::new();
+        push_line(&mut text, &"a".repeat(160));
+        let lines: Vec<&str> = text.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        // Kontynuacja zaczyna się spacją, która wlicza się do limitu
+        assert!(lines[1].starts_with(' ') && lines[1].len() == 75);
+        assert_eq!(lines.concat().replace(' ', ""), "a".repeat(160));
+    }
+
+    #[test]
+    fn folding_does_not_split_multibyte_characters() {
+        let mut text = String::new();
+        let line = "ż".repeat(50);
+        push_line(&mut text, &line);
+        for part in text.trim_end_matches("\r\n").split("\r\n") {
+            assert!(part.len() <= 75, "{}", part.len());
+        }
+        // 37 znaków po 2 bajty w pierwszej linii - 38. przekroczyłby limit
+        assert!(text.starts_with(&format!("{}\r\n ", "ż".repeat(37))));
+        assert_eq!(text.replace("\r\n ", "").trim_end_matches("\r\n"), line);
+    }
+}
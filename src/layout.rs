@@ -0,0 +1,347 @@
+use anyhow::{bail, Result};
+use rust_xlsxwriter::{Format, Worksheet};
+use serde::{Deserialize, Serialize};
+
+use crate::compliance;
+use crate::config;
+use crate::excel_merger::{excel_to_date, EmployeeData};
+
+const LAYOUTS_FILE: &str = "uklady.json";
+pub const DEFAULT_LAYOUT: &str = "Pełny";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Nazwisko,
+    Imie,
+    Pesel,
+    Dzial,
+    NrZaswiadczenia,
+    DataWystawienia,
+    DataOd,
+    DataDo,
+    Dni,
+    NaOpieke,
+    PobytWSzpitalu,
+    Status,
+    Wskazania,
+    Weryfikacja,
+    // Dowolna kolumna z listy pracowników, wyszukiwana po nagłówku
+    Kolumna(String),
+}
+
+impl Field {
+    pub const BUILT_IN: [Field; 14] = [
+        Field::Nazwisko,
+        Field::Imie,
+        Field::Pesel,
+        Field::Dzial,
+        Field::NrZaswiadczenia,
+        Field::DataWystawienia,
+        Field::DataOd,
+        Field::DataDo,
+        Field::Dni,
+        Field::NaOpieke,
+        Field::PobytWSzpitalu,
+        Field::Status,
+        Field::Wskazania,
+        Field::Weryfikacja,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            Field::Nazwisko => "Nazwisko",
+            Field::Imie => "Imię",
+            Field::Pesel => "PESEL",
+            Field::Dzial => "Dział",
+            Field::NrZaswiadczenia => "Seria i nr zaśw.",
+            Field::DataWystawienia => "Data wyst.",
+            Field::DataOd => "Data od",
+            Field::DataDo => "Data do",
+            Field::Dni => "Dni",
+            Field::NaOpieke => "Na opiekę",
+            Field::PobytWSzpitalu => "Pobyt w szpitalu",
+            Field::Status => "Status zaśw.",
+            Field::Wskazania => "Wskazania lekarskie",
+            Field::Weryfikacja => "Weryfikacja",
+            Field::Kolumna(name) => name,
+        }
+    }
+
+    // Klucz pola w eksportach JSON - nazwa pola z pliku układów albo nagłówek kolumny z listy pracowników
+    pub fn key(&self) -> String {
+        match self {
+            Field::Kolumna(name) => name.clone(),
+            field => serde_json::to_value(field)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn date(&self, employee: &EmployeeData) -> Option<Option<f64>> {
+        match self {
+            Field::DataWystawienia => Some(employee.data_wystawienia),
+            Field::DataOd => Some(employee.data_od),
+            Field::DataDo => Some(employee.data_do),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutColumn {
+    pub pole: Field,
+    pub naglowek: String,
+    // Format liczby lub daty Excela, np. "dd.mm.yyyy" (pusty - format domyślny)
+    #[serde(default)]
+    pub format: String,
+    // Szerokość kolumny (None - dopasowana do treści)
+    #[serde(default)]
+    pub szerokosc: Option<f64>,
+}
+
+impl LayoutColumn {
+    pub fn new(pole: Field) -> Self {
+        Self {
+            naglowek: pole.label().to_string(),
+            pole,
+            format: String::new(),
+            szerokosc: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportLayout {
+    pub nazwa: String,
+    pub kolumny: Vec<LayoutColumn>,
+}
+
+impl Default for ReportLayout {
+    fn default() -> Self {
+        built_in().remove(0)
+    }
+}
+
+impl ReportLayout {
+    fn new(nazwa: &str, fields: &[Field]) -> Self {
+        Self {
+            nazwa: nazwa.to_string(),
+            kolumny: fields.iter().cloned().map(LayoutColumn::new).collect(),
+        }
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.kolumny.iter().map(|column| column.naglowek.clone()).collect()
+    }
+
+    pub fn position(&self, field: &Field) -> Option<usize> {
+        self.kolumny.iter().position(|column| column.pole == *field)
+    }
+
+    // Nagłówki kolumn z listy pracowników, które trzeba wczytać dla tego układu
+    pub fn roster_columns(&self) -> Vec<String> {
+        self.kolumny
+            .iter()
+            .filter_map(|column| match &column.pole {
+                Field::Kolumna(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Dopisuje na końcu kolumny z listy pracowników, których układ jeszcze nie zawiera;
+    // zwraca nazwy pominiętych kolumn
+    pub fn add_roster_columns(&mut self, names: &[String]) -> Vec<String> {
+        let mut skipped = Vec::new();
+        for name in names {
+            let lowercase = name.trim().to_lowercase();
+            if self.kolumny.iter().any(|column| column.naglowek.to_lowercase() == lowercase) {
+                skipped.push(name.clone());
+                continue;
+            }
+            self.kolumny.push(LayoutColumn::new(Field::Kolumna(name.trim().to_string())));
+        }
+        skipped
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.nazwa.trim().is_empty() {
+            bail!("Układ raportu musi mieć nazwę");
+        }
+        if self.kolumny.is_empty() {
+            bail!("Układ {} nie zawiera żadnej kolumny", self.nazwa);
+        }
+        // Tabela Excela wymaga niepowtarzalnych, niepustych nagłówków
+        let mut seen = std::collections::HashSet::new();
+        for column in &self.kolumny {
+            let header = column.naglowek.trim().to_lowercase();
+            if header.is_empty() {
+                bail!("Kolumna {} w układzie {} nie ma nagłówka", column.pole.label(), self.nazwa);
+            }
+            if !seen.insert(header) {
+                bail!("Powtórzony nagłówek {} w układzie {}", column.naglowek, self.nazwa);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn built_in() -> Vec<ReportLayout> {
+    vec![
+        ReportLayout::new(
+            DEFAULT_LAYOUT,
+            &[
+                Field::Nazwisko,
+                Field::Imie,
+                Field::Pesel,
+                Field::DataOd,
+                Field::DataDo,
+                Field::NaOpieke,
+                Field::PobytWSzpitalu,
+                Field::Status,
+                Field::Weryfikacja,
+            ],
+        ),
+        ReportLayout::new(
+            "Płace",
+            &[
+                Field::Nazwisko,
+                Field::Imie,
+                Field::Pesel,
+                Field::NrZaswiadczenia,
+                Field::DataWystawienia,
+                Field::DataOd,
+                Field::DataDo,
+                Field::Dni,
+                Field::NaOpieke,
+                Field::PobytWSzpitalu,
+                Field::Status,
+            ],
+        ),
+        ReportLayout::new(
+            "Zarząd",
+            &[
+                Field::Dzial,
+                Field::Nazwisko,
+                Field::Imie,
+                Field::DataOd,
+                Field::DataDo,
+                Field::Dni,
+                Field::Status,
+            ],
+        ),
+    ]
+}
+
+// Układy wbudowane i zapisane przez użytkownika; zapisany układ o tej samej nazwie zastępuje wbudowany
+pub fn all() -> Result<Vec<ReportLayout>> {
+    let saved: Vec<ReportLayout> = config::load(LAYOUTS_FILE)?;
+    let mut layouts = built_in();
+    for layout in saved {
+        match layouts.iter_mut().find(|existing| existing.nazwa == layout.nazwa) {
+            Some(existing) => *existing = layout,
+            None => layouts.push(layout),
+        }
+    }
+    Ok(layouts)
+}
+
+pub fn find(name: &str) -> Result<ReportLayout> {
+    let layouts = all()?;
+    let names: Vec<&str> = layouts.iter().map(|layout| layout.nazwa.as_str()).collect();
+    match layouts.iter().find(|layout| layout.nazwa.to_lowercase() == name.trim().to_lowercase()) {
+        Some(layout) => Ok(layout.clone()),
+        None => bail!("Nieznany układ raportu: {} (dostępne: {})", name, names.join(", ")),
+    }
+}
+
+pub fn save(layout: &ReportLayout) -> Result<std::path::PathBuf> {
+    layout.validate()?;
+    let mut saved: Vec<ReportLayout> = config::load(LAYOUTS_FILE)?;
+    match saved.iter_mut().find(|existing| existing.nazwa == layout.nazwa) {
+        Some(existing) => *existing = layout.clone(),
+        None => saved.push(layout.clone()),
+    }
+    config::save(LAYOUTS_FILE, &saved)
+}
+
+pub fn layouts_file() -> std::path::PathBuf {
+    config::config_dir().join(LAYOUTS_FILE)
+}
+
+// Wartość pola jako tekst - daty w formacie DD.MM.RRRR
+pub fn text_value(employee: &EmployeeData, field: &Field) -> String {
+    if let Some(date) = field.date(employee) {
+        return date
+            .and_then(excel_to_date)
+            .map(|date| date.format("%d.%m.%Y").to_string())
+            .unwrap_or_default();
+    }
+    match field {
+        Field::Nazwisko => employee.nazwisko.clone(),
+        Field::Imie => employee.imie.clone(),
+        Field::Pesel => employee.pesel.clone(),
+        Field::Dzial => employee.dzial.clone(),
+        Field::NrZaswiadczenia => employee.nr_zaswiadczenia.clone(),
+        Field::Dni => days(employee).map(|days| days.to_string()).unwrap_or_default(),
+        Field::NaOpieke => employee.na_opieke.clone(),
+        Field::PobytWSzpitalu => employee.pobyt_w_szpitalu.clone(),
+        Field::Status => employee.status.clone(),
+        Field::Wskazania => employee.wskazania.clone(),
+        Field::Weryfikacja => compliance::flags_text(employee),
+        Field::Kolumna(name) => employee.dodatkowa(name).unwrap_or_default().to_string(),
+        Field::DataWystawienia | Field::DataOd | Field::DataDo => String::new(),
+    }
+}
+
+// Data jako liczba Excela (None dla pól, które nie są datami, i pustych dat)
+pub fn date_value(employee: &EmployeeData, field: &Field) -> Option<f64> {
+    field.date(employee).flatten()
+}
+
+// Liczba dni zwolnienia, o ile okres jest poprawny
+pub fn days(employee: &EmployeeData) -> Option<i64> {
+    employee
+        .okres()
+        .filter(|(start, end)| start <= end)
+        .map(|(start, end)| (end - start).num_days() + 1)
+}
+
+pub fn write_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    employee: &EmployeeData,
+    column: &LayoutColumn,
+    date_format: &Format,
+) -> Result<()> {
+    let custom_format = (!column.format.trim().is_empty()).then(|| Format::new().set_num_format(column.format.trim()));
+
+    if let Some(date) = column.pole.date(employee) {
+        let format = custom_format.as_ref().unwrap_or(date_format);
+        match date {
+            Some(excel_date) => sheet.write_number_with_format(row, col, excel_date, format)?,
+            None => sheet.write_blank(row, col, format)?,
+        };
+        return Ok(());
+    }
+
+    if column.pole == Field::Dni {
+        if let Some(days) = days(employee) {
+            match &custom_format {
+                Some(format) => sheet.write_number_with_format(row, col, days as f64, format)?,
+                None => sheet.write_number(row, col, days as f64)?,
+            };
+        }
+        return Ok(());
+    }
+
+    let text = text_value(employee, &column.pole);
+    match &custom_format {
+        Some(format) => sheet.write_string_with_format(row, col, text, format)?,
+        None => sheet.write_string(row, col, text)?,
+    };
+    Ok(())
+}
@@ -1,11 +1,43 @@
 #![windows_subsystem = "windows"]
 
+mod anomalies;
+mod attendance;
+mod calendar;
+mod changes;
+mod cli;
+mod compliance;
+mod config;
+mod control;
+mod encryption;
+mod episodes;
 mod excel_merger;
+mod history;
+mod html;
+mod ics;
+mod layout;
+mod manifest;
+mod output;
+mod payroll;
+mod pdf;
+mod privacy;
+mod return_to_work;
+mod snapshot;
+mod split;
+mod statistics;
+mod summary;
+mod template;
 
 use eframe::egui;
+use chrono::Datelike;
 use rfd::FileDialog;
 use std::path::PathBuf;
-use excel_merger::ExcelMerger;
+use changes::ChangesSource;
+use excel_merger::{ExcelMerger, MergeOptions};
+use ics::IcsOptions;
+use layout::{Field, LayoutColumn, ReportLayout};
+use output::{CsvEncoding, CsvOptions, OutputFormat};
+use payroll::{PayrollOptions, PayrollProfile};
+use privacy::PrivacyMode;
 
 struct ExcelMergerApp {
     first_file: Option<PathBuf>,
@@ -17,13 +49,67 @@ struct ExcelMergerApp {
     available_sheets_2: Vec<String>,
     selected_sheet_1_index: Option<usize>,
     selected_sheet_2_index: Option<usize>,
+    return_list_enabled: bool,
+    return_window_days: u32,
+    attendance_enabled: bool,
+    attendance_year: i32,
+    attendance_month: u32,
+    statistics_enabled: bool,
+    statistics_start: chrono::NaiveDate,
+    statistics_end: chrono::NaiveDate,
+    statistics_json: bool,
+    check_anomalies: bool,
+    control_enabled: bool,
+    control_criteria: control::ControlCriteria,
+    episodes_enabled: bool,
+    episode_gap_days: u32,
+    snapshot_date: chrono::NaiveDate,
+    // Kolumny listy pracowników poza Nazwiskiem, Imieniem i PESEL-em, z zaznaczeniem do raportu
+    roster_columns: Vec<(String, bool)>,
+    split_enabled: bool,
+    split_column: String,
+    split_template: String,
+    charts_enabled: bool,
+    layouts: Vec<ReportLayout>,
+    layout_index: usize,
+    // Kopia edytowanego układu (Some - okno edycji jest otwarte)
+    layout_editor: Option<ReportLayout>,
+    template_file: Option<PathBuf>,
+    append_enabled: bool,
+    replace_sheet: bool,
+    // None - format według rozszerzenia pliku wynikowego
+    output_format: Option<OutputFormat>,
+    csv_options: CsvOptions,
+    company: String,
+    ics_options: IcsOptions,
+    payroll_enabled: bool,
+    payroll_profiles: Vec<PayrollProfile>,
+    payroll_index: usize,
+    privacy: PrivacyMode,
+    encryption_enabled: bool,
+    password: String,
+    history_enabled: bool,
+    changes_enabled: bool,
+    // Wcześniejszy raport do porównania (None - poprzednie uruchomienie z historii)
+    changes_file: Option<PathBuf>,
+    // Wczytana historia importów (Some - okno historii jest otwarte)
+    history_window: Option<Vec<history::ImportEntry>>,
     log: String,
     merger: ExcelMerger,
 }
 
 impl Default for ExcelMergerApp {
     fn default() -> Self {
-        let default_output = format!("L4_{}.xlsx", chrono::Local::now().format("%d-%m-%Y"));
+        let today = chrono::Local::now().date_naive();
+        let default_output = format!("L4_{}.xlsx", today.format("%d-%m-%Y"));
+        let (layouts, mut log) = match layout::all() {
+            Ok(layouts) => (layouts, String::new()),
+            Err(e) => (layout::built_in(), format!("Błąd wczytywania układów raportu: {}\n", e)),
+        };
+        let payroll_profiles = payroll::all().unwrap_or_else(|e| {
+            log.push_str(&format!("Błąd wczytywania profili płacowych: {}\n", e));
+            payroll::built_in()
+        });
         Self {
             first_file: None,
             second_file: None,
@@ -34,7 +120,47 @@ impl Default for ExcelMergerApp {
             available_sheets_2: Vec::new(),
             selected_sheet_1_index: None,
             selected_sheet_2_index: None,
-            log: String::new(),
+            return_list_enabled: false,
+            return_window_days: 14,
+            attendance_enabled: false,
+            attendance_year: today.year(),
+            attendance_month: today.month(),
+            statistics_enabled: false,
+            statistics_start: today.with_day(1).unwrap(),
+            statistics_end: today,
+            statistics_json: false,
+            check_anomalies: true,
+            control_enabled: false,
+            control_criteria: control::ControlCriteria::default(),
+            episodes_enabled: false,
+            episode_gap_days: 0,
+            snapshot_date: today,
+            roster_columns: Vec::new(),
+            split_enabled: false,
+            split_column: String::new(),
+            split_template: split::DEFAULT_TEMPLATE.to_string(),
+            charts_enabled: true,
+            layouts,
+            layout_index: 0,
+            layout_editor: None,
+            template_file: None,
+            append_enabled: false,
+            replace_sheet: false,
+            output_format: None,
+            csv_options: CsvOptions::default(),
+            company: String::new(),
+            ics_options: IcsOptions::default(),
+            payroll_enabled: false,
+            payroll_profiles,
+            payroll_index: 0,
+            privacy: PrivacyMode::default(),
+            encryption_enabled: false,
+            password: String::new(),
+            history_enabled: false,
+            changes_enabled: false,
+            changes_file: None,
+            history_window: None,
+            log,
             merger: ExcelMerger::new(),
         }
     }
@@ -44,6 +170,271 @@ impl ExcelMergerApp {
     fn log_message(&mut self, message: String) {
         self.log.push_str(&format!("{}\n", message));
     }
+
+    fn check_absent(&mut self) {
+        if let (Some(first), Some(second), Some(first_sheet), Some(second_sheet)) = (
+            &self.first_file,
+            &self.second_file,
+            &self.first_sheet,
+            &self.second_sheet,
+        ) {
+            let result = self.merger.absent_on(first, second, first_sheet, second_sheet, self.snapshot_date);
+            let messages: Vec<String> = self.merger.messages.drain(..).collect();
+            for message in messages {
+                self.log_message(message);
+            }
+            match result {
+                Ok(absent) => {
+                    for employee in absent {
+                        self.log_message(employee.to_string());
+                    }
+                }
+                Err(e) => self.log_message(format!("Błąd: {}", e)),
+            }
+        } else {
+            self.log_message("Proszę wybrać wszystkie wymagane pliki i arkusze.".to_string());
+        }
+    }
+
+    // Sprawdza pliki wejściowe i wynikowe wskazanego manifestu
+    fn verify_report(&mut self) {
+        let Some(manifest_file) = FileDialog::new().add_filter("Manifest", &["json", "zip"]).pick_file() else {
+            return;
+        };
+        // Zaszyfrowany manifest jest otwierany hasłem z pola "Szyfruj hasłem"
+        let password = (!self.password.is_empty()).then_some(self.password.as_str());
+        match manifest::verify(&manifest_file, &[], password) {
+            Ok((manifest, checks)) => {
+                self.log_message(format!(
+                    "\nWeryfikacja manifestu {} (wersja {}, utworzono {}):",
+                    manifest_file.display(),
+                    manifest.wersja,
+                    manifest.utworzono
+                ));
+                for check in &checks {
+                    self.log_message(check.line());
+                }
+                match manifest::ensure_valid(&checks) {
+                    Ok(()) => self.log_message("Raport zgodny z manifestem".to_string()),
+                    Err(e) => self.log_message(format!("Błąd: {}", e)),
+                }
+            }
+            Err(e) => self.log_message(format!("Błąd: {}", e)),
+        }
+    }
+
+    fn load_roster_columns(&mut self) {
+        self.roster_columns = match (&self.first_file, &self.first_sheet) {
+            (Some(path), Some(sheet)) => self
+                .merger
+                .get_column_names(path, sheet)
+                .into_iter()
+                // Pierwsze trzy kolumny to Nazwisko, Imię i PESEL - są zawsze w raporcie
+                .filter(|(col, _)| *col >= 3)
+                .map(|(_, name)| (name, false))
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+
+    fn merge_options(&self) -> MergeOptions {
+        MergeOptions {
+            return_window_days: self.return_list_enabled.then_some(self.return_window_days),
+            attendance_month: self.attendance_enabled.then_some((self.attendance_year, self.attendance_month)),
+            statistics_period: self.statistics_enabled.then_some((self.statistics_start, self.statistics_end)),
+            statistics_json: match &self.output_file {
+                Some(output) if self.statistics_enabled && self.statistics_json => {
+                    Some(output.with_extension("statystyki.json"))
+                }
+                _ => None,
+            },
+            check_anomalies: self.check_anomalies,
+            control_criteria: self.control_enabled.then(|| self.control_criteria.clone()),
+            episode_gap_days: self.episodes_enabled.then_some(self.episode_gap_days),
+            extra_columns: self
+                .roster_columns
+                .iter()
+                .filter(|(_, selected)| *selected)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            split: (self.split_enabled && !self.split_column.is_empty()).then(|| split::SplitOptions {
+                column: self.split_column.clone(),
+                file_name_template: self.split_template.clone(),
+            }),
+            charts: self.charts_enabled,
+            layout: self.layouts.get(self.layout_index).cloned().unwrap_or_default(),
+            template: self.template_file.clone(),
+            append: self.append_enabled,
+            replace_sheet: self.replace_sheet,
+            format: self.output_format,
+            csv: self.csv_options.clone(),
+            company: self.company.clone(),
+            ics: self.ics_options.clone(),
+            payroll: match (&self.output_file, self.payroll_profiles.get(self.payroll_index)) {
+                (Some(output), Some(profile)) if self.payroll_enabled => Some(PayrollOptions {
+                    profile: profile.clone(),
+                    file: output.with_extension(format!("place.{}", profile.extension())),
+                }),
+                _ => None,
+            },
+            privacy: self.privacy,
+            password: self.encryption_enabled.then(|| self.password.clone()),
+            history: self.history_enabled && !self.encryption_enabled,
+            changes: self.changes_enabled.then(|| match &self.changes_file {
+                Some(file) => ChangesSource::File(file.clone()),
+                None => ChangesSource::PreviousRun,
+            }),
+        }
+    }
+
+    fn show_layout_editor(&mut self, ctx: &egui::Context) {
+        let roster_fields: Vec<Field> = self
+            .roster_columns
+            .iter()
+            .map(|(name, _)| Field::Kolumna(name.clone()))
+            .collect();
+        let Some(draft) = &mut self.layout_editor else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Układ raportu")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Nazwa:");
+                    ui.text_edit_singleline(&mut draft.nazwa);
+                });
+
+                let mut move_up = None;
+                let mut remove = None;
+                egui::Grid::new("layout_grid").striped(true).show(ui, |ui| {
+                    ui.label("Pole");
+                    ui.label("Nagłówek");
+                    ui.label("Format");
+                    ui.label("Szerokość");
+                    ui.end_row();
+                    for (idx, column) in draft.kolumny.iter_mut().enumerate() {
+                        ui.label(column.pole.label());
+                        ui.add(egui::TextEdit::singleline(&mut column.naglowek).desired_width(140.0));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut column.format)
+                                .hint_text("np. dd.mm.yyyy")
+                                .desired_width(90.0),
+                        );
+                        ui.horizontal(|ui| {
+                            let mut fixed = column.szerokosc.is_some();
+                            if ui.checkbox(&mut fixed, "").changed() {
+                                column.szerokosc = fixed.then_some(15.0);
+                            }
+                            match &mut column.szerokosc {
+                                Some(width) => {
+                                    ui.add(egui::DragValue::new(width).clamp_range(2.0..=100.0));
+                                }
+                                None => {
+                                    ui.label("auto");
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.small_button("⬆").clicked() && idx > 0 {
+                                move_up = Some(idx);
+                            }
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(idx);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                if let Some(idx) = move_up {
+                    draft.kolumny.swap(idx - 1, idx);
+                }
+                if let Some(idx) = remove {
+                    draft.kolumny.remove(idx);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Dodaj kolumnę:");
+                    egui::ComboBox::from_id_source("layout_add_column")
+                        .selected_text("Wybierz pole")
+                        .show_ui(ui, |ui| {
+                            for field in Field::BUILT_IN.iter().chain(roster_fields.iter()) {
+                                if ui.selectable_label(false, field.label()).clicked() {
+                                    draft.kolumny.push(LayoutColumn::new(field.clone()));
+                                }
+                            }
+                        });
+                });
+                if ui.button("💾 Zapisz układ").clicked() {
+                    save = true;
+                }
+            });
+
+        if save {
+            let draft = draft.clone();
+            match layout::save(&draft) {
+                Ok(path) => {
+                    self.log_message(format!("Zapisano układ {} w pliku {}", draft.nazwa, path.display()));
+                    match layout::all() {
+                        Ok(layouts) => self.layouts = layouts,
+                        Err(e) => self.log_message(format!("Błąd wczytywania układów raportu: {}", e)),
+                    }
+                    self.layout_index = self
+                        .layouts
+                        .iter()
+                        .position(|layout| layout.nazwa == draft.nazwa)
+                        .unwrap_or(0);
+                    self.layout_editor = None;
+                }
+                Err(e) => self.log_message(format!("Błąd: {}", e)),
+            }
+        } else if !open {
+            self.layout_editor = None;
+        }
+    }
+
+    fn show_history(&mut self, ctx: &egui::Context) {
+        let Some(imports) = &self.history_window else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("Historia importów")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if imports.is_empty() {
+                    ui.label("Historia importów jest pusta");
+                }
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("history_grid").striped(true).show(ui, |ui| {
+                        for header in ["Nr", "Czas", "Plik L4", "Okres", "Zaświadczenia", "Dopasowane", "Nowe", "Zmienione"] {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        ui.end_row();
+                        for entry in imports {
+                            ui.label(entry.id.to_string());
+                            ui.label(entry.czas.get(..16).unwrap_or(&entry.czas).replace('T', " "));
+                            ui.label(&entry.plik_l4);
+                            ui.label(format!("{} - {}", entry.okres_od, entry.okres_do));
+                            ui.label(entry.zaswiadczenia.to_string());
+                            ui.label(entry.dopasowane.to_string());
+                            ui.label(entry.nowe.to_string());
+                            ui.label(entry.zmienione.to_string());
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.label(format!("Baza historii: {}", history::database_file().display()));
+            });
+
+        if !open {
+            self.history_window = None;
+        }
+    }
 }
 
 impl eframe::App for ExcelMergerApp {
@@ -94,6 +485,7 @@ impl eframe::App for ExcelMergerApp {
                                         if !self.available_sheets_1.is_empty() {
                                             self.first_sheet = Some(self.available_sheets_1[0].clone());
                                         }
+                                        self.load_roster_columns();
                                     }
                                 }
                                 if let Some(path) = &self.first_file {
@@ -106,6 +498,7 @@ impl eframe::App for ExcelMergerApp {
                             });
                             ui.horizontal(|ui| {
                                 ui.label(egui::RichText::new("Arkusz:").size(14.0));
+                                let previous_sheet = self.first_sheet.clone();
                                 egui::ComboBox::from_id_source("sheet1_combo")
                                     .width(ui.available_width() - 50.0)
                                     .selected_text(self.first_sheet.as_deref().unwrap_or("Wybierz arkusz"))
@@ -116,7 +509,18 @@ impl eframe::App for ExcelMergerApp {
                                             }
                                         }
                                     });
+                                if self.first_sheet != previous_sheet {
+                                    self.load_roster_columns();
+                                }
                             });
+                            if !self.roster_columns.is_empty() {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Dodatkowe kolumny w raporcie:");
+                                    for (name, selected) in &mut self.roster_columns {
+                                        ui.checkbox(selected, name.as_str());
+                                    }
+                                });
+                            }
                             ui.add_space(5.0);
                             ui.label(
                                 egui::RichText::new("Wymagane kolumny: Nazwisko, Imię, Pesel")
@@ -189,6 +593,229 @@ impl eframe::App for ExcelMergerApp {
                                 }
                                 ui.add_space(ui.available_width());
                             });
+                            ui.horizontal(|ui| {
+                                ui.label("Format:");
+                                egui::ComboBox::from_id_source("output_format_combo")
+                                    .selected_text(self.output_format.map(|format| format.label()).unwrap_or("według rozszerzenia"))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut self.output_format, None, "według rozszerzenia");
+                                        for format in OutputFormat::ALL {
+                                            ui.selectable_value(&mut self.output_format, Some(format), format.label());
+                                        }
+                                    });
+                                let format = self.output_format.unwrap_or_else(|| {
+                                    self.output_file.as_deref().map(OutputFormat::from_path).unwrap_or(OutputFormat::Xlsx)
+                                });
+                                if format == OutputFormat::Csv {
+                                    ui.label("separator:");
+                                    egui::ComboBox::from_id_source("csv_delimiter_combo")
+                                        .selected_text(if self.csv_options.delimiter == '\t' { "tab".to_string() } else { self.csv_options.delimiter.to_string() })
+                                        .show_ui(ui, |ui| {
+                                            for (delimiter, label) in [(';', ";"), (',', ","), ('\t', "tab"), ('|', "|")] {
+                                                ui.selectable_value(&mut self.csv_options.delimiter, delimiter, label);
+                                            }
+                                        });
+                                    ui.label("kodowanie:");
+                                    egui::ComboBox::from_id_source("csv_encoding_combo")
+                                        .selected_text(self.csv_options.encoding.label())
+                                        .show_ui(ui, |ui| {
+                                            for encoding in CsvEncoding::ALL {
+                                                ui.selectable_value(&mut self.csv_options.encoding, encoding, encoding.label());
+                                            }
+                                        });
+                                }
+                                if matches!(format, OutputFormat::Pdf | OutputFormat::Html) {
+                                    ui.label("firma:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.company).desired_width(200.0));
+                                }
+                                if format == OutputFormat::Ics {
+                                    ui.label("tytuł:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.ics_options.summary).desired_width(200.0))
+                                        .on_hover_text("Znaczniki: {imie}, {nazwisko}, {dzial}, {nr_zaswiadczenia} lub nagłówek kolumny z listy pracowników");
+                                    ui.checkbox(&mut self.ics_options.details, "dane medyczne w opisie");
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Dane osobowe:");
+                                egui::ComboBox::from_id_source("privacy_combo")
+                                    .selected_text(self.privacy.label())
+                                    .show_ui(ui, |ui| {
+                                        for mode in PrivacyMode::ALL {
+                                            ui.selectable_value(&mut self.privacy, mode, mode.label());
+                                        }
+                                    });
+                                ui.checkbox(&mut self.encryption_enabled, "Szyfruj hasłem");
+                                ui.add_enabled(
+                                    self.encryption_enabled,
+                                    egui::TextEdit::singleline(&mut self.password).password(true).desired_width(150.0)
+                                );
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Szablon:");
+                                if ui.button("Wybierz").clicked() {
+                                    if let Some(path) = FileDialog::new()
+                                        .add_filter("Excel", &["xlsx"])
+                                        .pick_file() {
+                                        self.template_file = Some(path);
+                                    }
+                                }
+                                match &self.template_file {
+                                    Some(path) => {
+                                        ui.label(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+                                        if ui.small_button("✖").clicked() {
+                                            self.template_file = None;
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("brak - nowy skoroszyt");
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                let sheet_name = self.merge_options().period().0.format("%Y-%m").to_string();
+                                ui.add_enabled(
+                                    self.template_file.is_none(),
+                                    egui::Checkbox::new(
+                                        &mut self.append_enabled,
+                                        format!("Dopisz arkusz {} do istniejącego pliku", sheet_name),
+                                    ),
+                                );
+                                ui.add_enabled(
+                                    self.append_enabled && self.template_file.is_none(),
+                                    egui::Checkbox::new(&mut self.replace_sheet, "zastąp, jeśli istnieje"),
+                                );
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.return_list_enabled, "Lista powrotów do pracy w ciągu");
+                                ui.add_enabled(
+                                    self.return_list_enabled,
+                                    egui::DragValue::new(&mut self.return_window_days).clamp_range(1..=90)
+                                );
+                                ui.label("dni");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.attendance_enabled, "Lista obecności za");
+                                ui.add_enabled_ui(self.attendance_enabled, |ui| {
+                                    egui::ComboBox::from_id_source("attendance_month_combo")
+                                        .selected_text(calendar::MONTH_NAMES[self.attendance_month as usize - 1])
+                                        .show_ui(ui, |ui| {
+                                            for (idx, name) in calendar::MONTH_NAMES.iter().enumerate() {
+                                                ui.selectable_value(&mut self.attendance_month, idx as u32 + 1, *name);
+                                            }
+                                        });
+                                    ui.add(egui::DragValue::new(&mut self.attendance_year).clamp_range(2000..=2100));
+                                });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.statistics_enabled, "Statystyki absencji od");
+                                ui.add_enabled_ui(self.statistics_enabled, |ui| {
+                                    ui.add(egui_extras::DatePickerButton::new(&mut self.statistics_start).id_source("statistics_start"));
+                                    ui.label("do");
+                                    ui.add(egui_extras::DatePickerButton::new(&mut self.statistics_end).id_source("statistics_end"));
+                                    ui.checkbox(&mut self.statistics_json, "także JSON");
+                                });
+                                if self.statistics_enabled && self.statistics_start > self.statistics_end {
+                                    ui.colored_label(egui::Color32::RED, "Początek okresu jest po jego końcu");
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Układ raportu:");
+                                egui::ComboBox::from_id_source("layout_combo")
+                                    .selected_text(self.layouts.get(self.layout_index).map(|layout| layout.nazwa.as_str()).unwrap_or(""))
+                                    .show_ui(ui, |ui| {
+                                        for (idx, layout) in self.layouts.iter().enumerate() {
+                                            ui.selectable_value(&mut self.layout_index, idx, layout.nazwa.as_str());
+                                        }
+                                    });
+                                if ui.button("Edytuj układ").clicked() {
+                                    self.layout_editor = self.layouts.get(self.layout_index).cloned();
+                                }
+                            });
+                            ui.checkbox(&mut self.charts_enabled, "Wykresy w arkuszu \"Podsumowanie\"");
+                            ui.checkbox(&mut self.check_anomalies, "Sprawdź dane pod kątem anomalii (arkusz \"Anomalie\")");
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(
+                                    !self.encryption_enabled,
+                                    egui::Checkbox::new(&mut self.history_enabled, "Zapisuj zaświadczenia w historii"),
+                                )
+                                .on_disabled_hover_text("Baza historii nie jest szyfrowana");
+                                if ui.button("Historia importów").clicked() {
+                                    match history::imports() {
+                                        Ok(imports) => self.history_window = Some(imports),
+                                        Err(e) => self.log_message(format!("Błąd: {}", e)),
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.changes_enabled, "Zmiany (arkusz \"Zmiany\") względem:");
+                                ui.add_enabled_ui(self.changes_enabled, |ui| {
+                                    if ui.button("Wybierz raport").clicked() {
+                                        if let Some(path) = FileDialog::new()
+                                            .add_filter("Excel", &["xlsx"])
+                                            .pick_file() {
+                                            self.changes_file = Some(path);
+                                        }
+                                    }
+                                    match &self.changes_file {
+                                        Some(path) => {
+                                            ui.label(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+                                            if ui.small_button("✖").clicked() {
+                                                self.changes_file = None;
+                                            }
+                                        }
+                                        None => {
+                                            ui.label("poprzednie uruchomienie");
+                                        }
+                                    }
+                                });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.episodes_enabled, "Epizody nieobecności, dopuszczalna przerwa");
+                                ui.add_enabled(
+                                    self.episodes_enabled,
+                                    egui::DragValue::new(&mut self.episode_gap_days).clamp_range(0..=30)
+                                );
+                                ui.label("dni");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.control_enabled, "Lista do kontroli: zwolnienie od");
+                                ui.add_enabled_ui(self.control_enabled, |ui| {
+                                    let criteria = &mut self.control_criteria;
+                                    ui.add(egui::DragValue::new(&mut criteria.min_days).clamp_range(1..=182));
+                                    ui.label("dni lub");
+                                    ui.add(egui::DragValue::new(&mut criteria.min_certificates).clamp_range(1..=20));
+                                    ui.label("zaśw. w");
+                                    ui.add(egui::DragValue::new(&mut criteria.lookback_days).clamp_range(1..=365));
+                                    ui.label("dni");
+                                    ui.checkbox(&mut criteria.only_bed_rest, "tylko \"chory powinien leżeć\"");
+                                });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.payroll_enabled, "Plik dla programu płacowego, profil");
+                                ui.add_enabled_ui(self.payroll_enabled, |ui| {
+                                    egui::ComboBox::from_id_source("payroll_profile_combo")
+                                        .selected_text(self.payroll_profiles.get(self.payroll_index).map(|profile| profile.nazwa.as_str()).unwrap_or(""))
+                                        .show_ui(ui, |ui| {
+                                            for (idx, profile) in self.payroll_profiles.iter().enumerate() {
+                                                ui.selectable_value(&mut self.payroll_index, idx, profile.nazwa.as_str());
+                                            }
+                                        });
+                                });
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.split_enabled, "Osobne pliki według kolumny");
+                                ui.add_enabled_ui(self.split_enabled, |ui| {
+                                    egui::ComboBox::from_id_source("split_column_combo")
+                                        .selected_text(if self.split_column.is_empty() { "Wybierz kolumnę" } else { self.split_column.as_str() })
+                                        .show_ui(ui, |ui| {
+                                            for (name, _) in &self.roster_columns {
+                                                ui.selectable_value(&mut self.split_column, name.clone(), name.as_str());
+                                            }
+                                        });
+                                    ui.label("nazwa pliku:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.split_template).desired_width(160.0));
+                                });
+                            });
                         });
 
                         // Przycisk uruchomienia
@@ -211,7 +838,8 @@ impl eframe::App for ExcelMergerApp {
                                     &self.first_sheet,
                                     &self.second_sheet,
                                 ) {
-                                    if let Err(e) = self.merger.merge_files(first, second, output, first_sheet, second_sheet) {
+                                    let options = self.merge_options();
+                                    if let Err(e) = self.merger.merge_files(first, second, output, first_sheet, second_sheet, &options) {
                                         self.log_message(format!("Błąd: {}", e));
                                     }
                                     let messages = self.merger.messages.clone();
@@ -224,6 +852,37 @@ impl eframe::App for ExcelMergerApp {
                                 }
                             }
                         });
+
+                        // Sekcja podglądu nieobecności w wybranym dniu
+                        ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new("🔎 Kto jest nieobecny w dniu").size(16.0));
+                                ui.add(egui_extras::DatePickerButton::new(&mut self.snapshot_date).id_source("snapshot_date"));
+                                if ui.add(
+                                    egui::Button::new("Sprawdź")
+                                        .fill(egui::Color32::from_rgb(33, 150, 243))
+                                        .rounding(6.0)
+                                ).clicked() {
+                                    self.check_absent();
+                                }
+                                ui.add_space(ui.available_width());
+                            });
+                        });
+
+                        // Weryfikacja raportu z manifestem sum kontrolnych
+                        ui.group(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new("🛡 Zgodność raportu z manifestem").size(16.0));
+                                if ui.add(
+                                    egui::Button::new("Weryfikuj")
+                                        .fill(egui::Color32::from_rgb(33, 150, 243))
+                                        .rounding(6.0)
+                                ).clicked() {
+                                    self.verify_report();
+                                }
+                                ui.add_space(ui.available_width());
+                            });
+                        });
                     });
 
                 // Logi
@@ -277,15 +936,23 @@ impl eframe::App for ExcelMergerApp {
                     });
                 });
             });
+
+        self.show_layout_editor(ctx);
+        self.show_history(ctx);
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([700.0, 650.0])
-            .with_min_inner_size([700.0, 650.0])
-            .with_max_inner_size([700.0, 650.0])
+            .with_inner_size([700.0, 760.0])
+            .with_min_inner_size([700.0, 760.0])
+            .with_max_inner_size([700.0, 760.0])
             .with_resizable(false)
             .with_title("L4 Filter")
             .with_transparent(false)
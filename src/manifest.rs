@@ -0,0 +1,373 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::encryption;
+use crate::excel_merger::MergeOptions;
+use crate::layout::ReportLayout;
+use crate::output::OutputFormat;
+
+// Plik z sumami kontrolnymi zapisywany obok pliku wynikowego, np. raport.manifest.json.
+// Skrót pliku wynikowego nie może być zapisany w nim samym, dlatego manifest jest osobnym plikiem.
+// Przy szyfrowaniu manifest też jest archiwum ZIP z hasłem (raport.manifest.json.zip).
+pub const EXTENSION: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    // Rodzaj pliku, np. "pracownicy", "l4", "wynik", "podzial"
+    pub rola: String,
+    pub plik: PathBuf,
+    // Arkusz, z którego wczytano dane (tylko pliki wejściowe)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arkusz: Option<String>,
+    pub rozmiar: u64,
+    pub sha256: String,
+}
+
+impl FileHash {
+    pub fn new(rola: &str, plik: &Path, arkusz: Option<&str>) -> Result<Self> {
+        let (rozmiar, sha256) = hash_file(plik)?;
+        Ok(Self {
+            rola: rola.to_string(),
+            plik: std::path::absolute(plik).unwrap_or_else(|_| plik.to_path_buf()),
+            arkusz: arkusz.map(str::to_string),
+            rozmiar,
+            sha256,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub program: String,
+    pub wersja: String,
+    pub utworzono: String,
+    pub pliki_wejsciowe: Vec<FileHash>,
+    pub ustawienia: Value,
+    pub pliki_wynikowe: Vec<FileHash>,
+}
+
+impl Manifest {
+    pub fn new(started: DateTime<Local>, inputs: Vec<FileHash>, ustawienia: Value, outputs: Vec<FileHash>) -> Self {
+        Self {
+            program: env!("CARGO_PKG_NAME").to_string(),
+            wersja: env!("CARGO_PKG_VERSION").to_string(),
+            utworzono: started.to_rfc3339(),
+            pliki_wejsciowe: inputs,
+            ustawienia,
+            pliki_wynikowe: outputs,
+        }
+    }
+
+    pub fn load(path: &Path, password: Option<&str>) -> Result<Self> {
+        let bytes = encryption::read_file(path, password)?;
+        serde_json::from_slice(&bytes).with_context(|| format!("Niepoprawny format manifestu {}", path.display()))
+    }
+
+    // Zapis tak jak plików wynikowych - z hasłem jako zaszyfrowane archiwum. Zwraca ścieżkę zapisanego pliku.
+    pub fn save(&self, path: &Path, password: Option<&str>) -> Result<PathBuf> {
+        encryption::write_file(path, serde_json::to_string_pretty(self)?.as_bytes(), password)
+            .with_context(|| format!("Nie można zapisać manifestu {}", path.display()))
+    }
+}
+
+pub fn manifest_path(output_file: &Path) -> PathBuf {
+    output_file.with_extension(EXTENSION)
+}
+
+// Manifest raportu wskazanego plikiem wynikowym, także zaszyfrowanym (raport.xlsx.zip -> raport.manifest.json.zip)
+pub fn find_manifest(output_file: &Path) -> PathBuf {
+    let encrypted = encryption::is_encrypted(output_file);
+    let output_file = if encrypted { output_file.with_extension("") } else { output_file.to_path_buf() };
+    let plain = manifest_path(&output_file);
+    let archive = encryption::target_path(&plain, true);
+    if encrypted || (!plain.exists() && archive.exists()) {
+        archive
+    } else {
+        plain
+    }
+}
+
+pub fn hash_file(path: &Path) -> Result<(u64, String)> {
+    let bytes = std::fs::read(path).with_context(|| format!("Nie można odczytać pliku {}", path.display()))?;
+    let hash = Sha256::digest(&bytes);
+    Ok((bytes.len() as u64, hash.iter().map(|byte| format!("{:02x}", byte)).collect()))
+}
+
+// Ustawienia, które wpływają na zawartość plików wynikowych; hasło nie jest zapisywane
+pub fn settings(options: &MergeOptions, format: OutputFormat, layout: &ReportLayout) -> Value {
+    let date = |date: chrono::NaiveDate| date.format("%Y-%m-%d").to_string();
+    let (period_start, period_end) = options.period();
+    json!({
+        "format": format.extension(),
+        "okres_od": date(period_start),
+        "okres_do": date(period_end),
+        "uklad": layout,
+        "dane_osobowe": options.privacy.key(),
+        "szyfrowanie": options.password.is_some(),
+        "powroty_dni": options.return_window_days,
+        "lista_obecnosci": options.attendance_month.map(|(year, month)| format!("{}-{:02}", year, month)),
+        "statystyki": options.statistics_period.map(|(start, end)| json!({ "od": date(start), "do": date(end) })),
+        "anomalie": options.check_anomalies,
+        "epizody_przerwa_dni": options.episode_gap_days,
+        "kontrole": options.control_criteria.as_ref().map(|criteria| json!({
+            "min_dni": criteria.min_days,
+            "min_zaswiadczen": criteria.min_certificates,
+            "okres_dni": criteria.lookback_days,
+            "tylko_lezacy": criteria.only_bed_rest,
+        })),
+        "podzial": options.split.as_ref().map(|split| json!({
+            "kolumna": split.column,
+            "szablon_nazwy": split.file_name_template,
+        })),
+        "wykresy": options.charts,
+        "szablon": options.template,
+        "dopisz": options.append,
+        "zastap_arkusz": options.replace_sheet,
+        "csv": (format == OutputFormat::Csv).then(|| json!({
+            "separator": options.csv.delimiter.to_string(),
+            "kodowanie": options.csv.encoding.label(),
+        })),
+        "firma": options.company,
+        "ics": (format == OutputFormat::Ics).then(|| json!({
+            "tytul": options.ics.summary,
+            "dane_medyczne": options.ics.details,
+        })),
+        "profil_placowy": options.payroll.as_ref().map(|payroll| &payroll.profile),
+        "historia": options.history,
+        "zmiany": options.changes.as_ref().map(|source| source.describe()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckResult {
+    Zgodny,
+    Rozny,
+    Brak,
+    // Plik wejściowy nadpisany przez plik wynikowy (skoroszyt w trybie dopisywania)
+    Nadpisany,
+}
+
+pub struct Check {
+    pub rola: String,
+    pub plik: PathBuf,
+    pub wejsciowy: bool,
+    pub wynik: CheckResult,
+}
+
+impl Check {
+    pub fn line(&self) -> String {
+        let result = match self.wynik {
+            CheckResult::Zgodny => "zgodny",
+            CheckResult::Rozny => "ZMIENIONY - suma SHA-256 inna niż w manifeście",
+            CheckResult::Brak => "brak pliku - nie sprawdzono",
+            CheckResult::Nadpisany => "stan sprzed dopisania arkusza - nie sprawdzono",
+        };
+        format!("{}: {} - {}", self.rola, self.plik.display(), result)
+    }
+}
+
+// Plik pod ścieżką z manifestu, a gdy go tam nie ma - o tej samej nazwie w katalogu manifestu
+fn locate(recorded: &Path, manifest_dir: &Path) -> Option<PathBuf> {
+    if recorded.exists() {
+        return Some(recorded.to_path_buf());
+    }
+    let moved = manifest_dir.join(recorded.file_name()?);
+    moved.exists().then_some(moved)
+}
+
+fn check(entry: &FileHash, path: Option<PathBuf>, wejsciowy: bool) -> Result<Check> {
+    let Some(path) = path else {
+        return Ok(Check { rola: entry.rola.clone(), plik: entry.plik.clone(), wejsciowy, wynik: CheckResult::Brak });
+    };
+    let (size, hash) = hash_file(&path)?;
+    let wynik = if size == entry.rozmiar && hash == entry.sha256 { CheckResult::Zgodny } else { CheckResult::Rozny };
+    Ok(Check { rola: entry.rola.clone(), plik: path, wejsciowy, wynik })
+}
+
+// Porównuje pliki z sumami z manifestu. Pliki wejściowe można wskazać w nowym położeniu
+// (rola i ścieżka); brak pliku wejściowego nie jest błędem, brak pliku wynikowego - jest.
+// Hasło jest potrzebne tylko do odczytu zaszyfrowanego manifestu.
+pub fn verify(manifest_file: &Path, inputs: &[(&str, PathBuf)], password: Option<&str>) -> Result<(Manifest, Vec<Check>)> {
+    let manifest = Manifest::load(manifest_file, password)?;
+    let manifest_dir = manifest_file.parent().unwrap_or(Path::new(""));
+
+    let mut checks = Vec::new();
+    for entry in &manifest.pliki_wejsciowe {
+        if manifest.pliki_wynikowe.iter().any(|output| output.plik == entry.plik) {
+            let wynik = CheckResult::Nadpisany;
+            checks.push(Check { rola: entry.rola.clone(), plik: entry.plik.clone(), wejsciowy: true, wynik });
+            continue;
+        }
+        let path = match inputs.iter().find(|(rola, _)| *rola == entry.rola) {
+            Some((_, path)) => Some(path.clone()),
+            None => locate(&entry.plik, manifest_dir),
+        };
+        checks.push(check(entry, path, true)?);
+    }
+    for entry in &manifest.pliki_wynikowe {
+        checks.push(check(entry, locate(&entry.plik, manifest_dir), false)?);
+    }
+    Ok((manifest, checks))
+}
+
+// Wynik weryfikacji jako błąd, jeśli którykolwiek plik różni się od manifestu albo brakuje pliku wynikowego
+pub fn ensure_valid(checks: &[Check]) -> Result<()> {
+    let changed = checks.iter().filter(|check| check.wynik == CheckResult::Rozny).count();
+    let missing_outputs = checks
+        .iter()
+        .filter(|check| !check.wejsciowy && check.wynik == CheckResult::Brak)
+        .count();
+    if changed > 0 || missing_outputs > 0 {
+        bail!(
+            "Raport niezgodny z manifestem: zmienione pliki: {}, brakujące pliki wynikowe: {}",
+            changed,
+            missing_outputs
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pusty katalog tymczasowy tego testu
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("l4-filter-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, text: &str) -> PathBuf {
+        std::fs::write(path, text).unwrap();
+        path.to_path_buf()
+    }
+
+    // Manifest raportu raport.xlsx z listą pracowników i plikiem L4
+    fn report(dir: &Path, password: Option<&str>) -> PathBuf {
+        let first = write(&dir.join("pracownicy.xlsx"), "pracownicy");
+        let second = write(&dir.join("l4.xlsx"), "l4");
+        let output = write(&dir.join("raport.xlsx"), "raport");
+        let inputs = vec![
+            FileHash::new("pracownicy", &first, Some("Arkusz1")).unwrap(),
+            FileHash::new("l4", &second, Some("Arkusz1")).unwrap(),
+        ];
+        let outputs = vec![FileHash::new("wynik", &output, None).unwrap()];
+        Manifest::new(Local::now(), inputs, json!({}), outputs)
+            .save(&manifest_path(&output), password)
+            .unwrap()
+    }
+
+    fn results(checks: &[Check]) -> Vec<(&str, CheckResult)> {
+        checks.iter().map(|check| (check.rola.as_str(), check.wynik)).collect()
+    }
+
+    #[test]
+    fn matching_report_is_valid() {
+        let dir = test_dir("manifest-zgodny");
+        let manifest_file = report(&dir, None);
+        assert_eq!(manifest_file, dir.join("raport.manifest.json"));
+        assert_eq!(find_manifest(&dir.join("raport.xlsx")), manifest_file);
+
+        let (manifest, checks) = verify(&manifest_file, &[], None).unwrap();
+        assert_eq!(manifest.pliki_wejsciowe[0].arkusz.as_deref(), Some("Arkusz1"));
+        assert_eq!(
+            results(&checks),
+            [("pracownicy", CheckResult::Zgodny), ("l4", CheckResult::Zgodny), ("wynik", CheckResult::Zgodny)]
+        );
+        assert!(ensure_valid(&checks).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn modified_or_missing_output_is_invalid() {
+        let dir = test_dir("manifest-zmieniony");
+        let manifest_file = report(&dir, None);
+
+        write(&dir.join("raport.xlsx"), "raport po zmianie");
+        let (_, checks) = verify(&manifest_file, &[], None).unwrap();
+        assert_eq!(checks[2].wynik, CheckResult::Rozny);
+        assert!(ensure_valid(&checks).is_err());
+
+        std::fs::remove_file(dir.join("raport.xlsx")).unwrap();
+        let (_, checks) = verify(&manifest_file, &[], None).unwrap();
+        assert_eq!(checks[2].wynik, CheckResult::Brak);
+        assert!(ensure_valid(&checks).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn moved_inputs_are_found_with_overrides() {
+        let dir = test_dir("manifest-przeniesiony");
+        let manifest_file = report(&dir, None);
+        let moved = dir.join("archiwum");
+        std::fs::create_dir(&moved).unwrap();
+        std::fs::rename(dir.join("pracownicy.xlsx"), moved.join("lista.xlsx")).unwrap();
+        std::fs::rename(dir.join("l4.xlsx"), moved.join("zus.xlsx")).unwrap();
+
+        // Brakujący plik wejściowy nie jest błędem, tylko nie jest sprawdzany
+        let (_, checks) = verify(&manifest_file, &[], None).unwrap();
+        assert_eq!(checks[0].wynik, CheckResult::Brak);
+        assert!(ensure_valid(&checks).is_ok());
+
+        let inputs = [("pracownicy", moved.join("lista.xlsx")), ("l4", moved.join("zus.xlsx"))];
+        let (_, checks) = verify(&manifest_file, &inputs, None).unwrap();
+        assert_eq!(
+            results(&checks),
+            [("pracownicy", CheckResult::Zgodny), ("l4", CheckResult::Zgodny), ("wynik", CheckResult::Zgodny)]
+        );
+        assert_eq!(checks[1].plik, moved.join("zus.xlsx"));
+
+        write(&moved.join("zus.xlsx"), "inny plik l4");
+        let (_, checks) = verify(&manifest_file, &inputs, None).unwrap();
+        assert_eq!(checks[1].wynik, CheckResult::Rozny);
+        assert!(ensure_valid(&checks).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn appended_workbook_is_not_checked_as_input() {
+        let dir = test_dir("manifest-dopisany");
+        let output = write(&dir.join("zestawienie.xlsx"), "przed dopisaniem");
+        let inputs = vec![FileHash::new("dopisywany", &output, None).unwrap()];
+        write(&output, "po dopisaniu");
+        let outputs = vec![FileHash::new("wynik", &output, None).unwrap()];
+        let manifest_file = Manifest::new(Local::now(), inputs, json!({}), outputs)
+            .save(&manifest_path(&output), None)
+            .unwrap();
+
+        let (_, checks) = verify(&manifest_file, &[], None).unwrap();
+        assert_eq!(
+            results(&checks),
+            [("dopisywany", CheckResult::Nadpisany), ("wynik", CheckResult::Zgodny)]
+        );
+        assert!(ensure_valid(&checks).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_manifest_requires_the_password() {
+        let dir = test_dir("manifest-szyfrowany");
+        let manifest_file = report(&dir, Some("haslo"));
+        assert_eq!(manifest_file, dir.join("raport.manifest.json.zip"));
+        assert!(!dir.join("raport.manifest.json").exists());
+        assert_eq!(find_manifest(&dir.join("raport.xlsx")), manifest_file);
+        assert_eq!(find_manifest(&dir.join("raport.xlsx.zip")), manifest_file);
+
+        assert!(verify(&manifest_file, &[], None).is_err());
+        assert!(verify(&manifest_file, &[], Some("inne haslo")).is_err());
+        let (_, checks) = verify(&manifest_file, &[], Some("haslo")).unwrap();
+        assert!(ensure_valid(&checks).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,447 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use rust_xlsxwriter::utility::{cell_range, column_number_to_name};
+use rust_xlsxwriter::{Color, ConditionalFormatFormula, Format, FormatBorder, Table, Workbook, Worksheet};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::excel_merger::{excel_to_date, is_yes, EmployeeData, MergeOptions};
+use crate::html::HtmlWriter;
+use crate::ics::IcsWriter;
+use crate::layout::{self, Field, ReportLayout};
+use crate::pdf::PdfWriter;
+use crate::privacy::PrivacyMode;
+use crate::summary::Summary;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Xlsx,
+    Csv,
+    Json,
+    // JSON w wierszach - jeden obiekt na wiersz raportu
+    Ndjson,
+    Pdf,
+    Html,
+    // Kalendarz iCalendar - wydarzenie całodniowe dla każdego zaświadczenia lub epizodu
+    Ics,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 7] = [
+        OutputFormat::Xlsx,
+        OutputFormat::Csv,
+        OutputFormat::Json,
+        OutputFormat::Ndjson,
+        OutputFormat::Pdf,
+        OutputFormat::Html,
+        OutputFormat::Ics,
+    ];
+
+    // Format według rozszerzenia pliku wynikowego; nieznane rozszerzenie oznacza XLSX
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "csv" | "txt" => OutputFormat::Csv,
+            "json" => OutputFormat::Json,
+            "ndjson" | "jsonl" => OutputFormat::Ndjson,
+            "pdf" => OutputFormat::Pdf,
+            "html" | "htm" => OutputFormat::Html,
+            "ics" => OutputFormat::Ics,
+            _ => OutputFormat::Xlsx,
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match OutputFormat::ALL.iter().find(|format| format.extension() == name.trim().to_lowercase()) {
+            Some(format) => Ok(*format),
+            None => bail!("Nieznany format wyniku: {} (dostępne: xlsx, csv, json, ndjson, pdf, html, ics)", name),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Html => "html",
+            OutputFormat::Ics => "ics",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Xlsx => "Excel (XLSX)",
+            OutputFormat::Csv => "CSV",
+            OutputFormat::Json => "JSON",
+            OutputFormat::Ndjson => "NDJSON",
+            OutputFormat::Pdf => "PDF",
+            OutputFormat::Html => "HTML",
+            OutputFormat::Ics => "iCalendar",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvEncoding {
+    Utf8,
+    // UTF-8 ze znacznikiem BOM - Excel rozpoznaje wtedy polskie znaki
+    Utf8Bom,
+    Windows1250,
+}
+
+impl CsvEncoding {
+    pub const ALL: [CsvEncoding; 3] = [CsvEncoding::Utf8, CsvEncoding::Utf8Bom, CsvEncoding::Windows1250];
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Ok(CsvEncoding::Utf8),
+            "utf8bom" => Ok(CsvEncoding::Utf8Bom),
+            "cp1250" | "windows1250" => Ok(CsvEncoding::Windows1250),
+            _ => bail!("Nieznane kodowanie: {} (dostępne: utf8, utf8-bom, cp1250)", name),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CsvEncoding::Utf8 => "UTF-8",
+            CsvEncoding::Utf8Bom => "UTF-8 z BOM",
+            CsvEncoding::Windows1250 => "Windows-1250",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub encoding: CsvEncoding,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self { delimiter: ';', encoding: CsvEncoding::Utf8Bom }
+    }
+}
+
+// Wynik dopasowania przekazywany do zapisu w wybranym formacie
+#[derive(Clone, Copy)]
+pub struct Report<'a> {
+    pub layout: &'a ReportLayout,
+    pub rows: &'a [EmployeeData],
+    // Wiersze L4 osób spoza listy pracowników
+    pub unmatched: &'a [EmployeeData],
+    // Tryb danych osobowych, w którym przygotowano wiersze
+    pub privacy: PrivacyMode,
+    // Numery wierszy źródłowych (EmployeeData::wiersz) zaświadczeń z anomaliami - sprawdzane na pełnych
+    // danych, bo po zamaskowaniu numerów PESEL różne osoby mogłyby wyglądać jak jedna
+    pub anomalies: &'a BTreeSet<usize>,
+    pub summary: &'a Summary,
+    pub period: (NaiveDate, NaiveDate),
+    pub started: DateTime<Local>,
+    pub first_file: &'a Path,
+    pub second_file: &'a Path,
+}
+
+pub trait ReportWriter {
+    // Zawartość pliku wynikowego; zapis na dysk należy do wywołującego
+    fn write(&self, report: &Report) -> Result<Vec<u8>>;
+}
+
+pub fn writer(format: OutputFormat, options: &MergeOptions) -> Box<dyn ReportWriter> {
+    match format {
+        OutputFormat::Xlsx => Box::new(XlsxWriter),
+        OutputFormat::Csv => Box::new(CsvWriter { options: options.csv.clone() }),
+        OutputFormat::Json => Box::new(JsonWriter { lines: false }),
+        OutputFormat::Ndjson => Box::new(JsonWriter { lines: true }),
+        OutputFormat::Pdf => Box::new(PdfWriter { company: options.company.clone() }),
+        OutputFormat::Html => Box::new(HtmlWriter { company: options.company.clone() }),
+        OutputFormat::Ics => Box::new(IcsWriter {
+            options: options.ics.clone(),
+            episode_gap_days: options.episode_gap_days,
+        }),
+    }
+}
+
+pub fn header_format() -> Format {
+    Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0x4F8_1BD))
+        .set_font_color(Color::RGB(0xFFFF_FF))
+        .set_border(FormatBorder::Thin)
+}
+
+pub fn date_format() -> Format {
+    Format::new().set_num_format("dd/mm/yyyy")
+}
+
+// Skoroszyt z jednym arkuszem "Dane" - bez arkuszy dodatkowych pełnego raportu
+pub struct XlsxWriter;
+
+impl ReportWriter for XlsxWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Dane")?;
+        write_data_sheet(sheet, report.layout, report.rows, report.anomalies)?;
+        Ok(workbook.save_to_buffer()?)
+    }
+}
+
+// Nagłówki i wiersze układu sformatowane jako tabela Excela
+pub fn write_data_sheet(
+    sheet: &mut Worksheet,
+    layout: &ReportLayout,
+    data: &[EmployeeData],
+    anomalies: &BTreeSet<usize>,
+) -> Result<()> {
+    let header_format = header_format();
+    let date_format = date_format();
+    for (col, header) in layout.headers().iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, header, &header_format)?;
+    }
+    let mut row = 1;
+    for employee in data {
+        if employee.source == "l4" {
+            for (col, column) in layout.kolumny.iter().enumerate() {
+                layout::write_cell(sheet, row, col as u16, employee, column, &date_format)?;
+            }
+            row += 1;
+        }
+    }
+    format_as_table(sheet, layout, data, anomalies)
+}
+
+// Tabela Excela z autofiltrem, zablokowany wiersz nagłówka, szerokości dopasowane do treści
+// i wyróżnienie wierszy: anulowanych, z anomaliami, pobytów w szpitalu i trwających zwolnień
+fn format_as_table(
+    sheet: &mut Worksheet,
+    layout: &ReportLayout,
+    data: &[EmployeeData],
+    anomalies: &BTreeSet<usize>,
+) -> Result<()> {
+    let last_col = layout.kolumny.len() as u16 - 1;
+    // Tabela musi mieć co najmniej jeden wiersz danych
+    let last_row = (data.len() as u32).max(1);
+    sheet.add_table(0, 0, last_row, last_col, &Table::new())?;
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    for (col, column) in layout.kolumny.iter().enumerate() {
+        if let Some(width) = column.szerokosc {
+            sheet.set_column_width(col as u16, width)?;
+        }
+    }
+
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let cancelled_format = Format::new().set_font_color(Color::RGB(0x80_8080)).set_font_strikethrough();
+    let anomaly_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xFF_C7CE))
+        .set_font_color(Color::RGB(0x9C_0006));
+    let hospital_format = Format::new().set_background_color(Color::RGB(0xDD_EBF7));
+    let ongoing_format = Format::new().set_background_color(Color::RGB(0xC6_EFCE));
+
+    // Reguły odwołują się do kolumn układu, więc reguła bez swojej kolumny jest pomijana
+    let cell = |field: Field| {
+        layout
+            .position(&field)
+            .map(|col| format!("${}2", column_number_to_name(col as u16)))
+    };
+    let mut rules = Vec::new();
+    if let Some(status) = cell(Field::Status) {
+        rules.push((format!("=ISNUMBER(SEARCH(\"anul\",{}))", status), &cancelled_format));
+    }
+    if let Some(hospital) = cell(Field::PobytWSzpitalu) {
+        rules.push((format!("=OR(LOWER(TRIM({0}))=\"tak\",LOWER(TRIM({0}))=\"t\")", hospital), &hospital_format));
+    }
+    if let (Some(start), Some(end)) = (cell(Field::DataOd), cell(Field::DataDo)) {
+        rules.push((format!("=AND({}<=TODAY(),{}>=TODAY())", start, end), &ongoing_format));
+    }
+
+    // Reguły w kolejności ważności - anulowane zaświadczenie nie jest dalej wyróżniane
+    for (rule, format) in &rules {
+        sheet.add_conditional_format(
+            1,
+            0,
+            last_row,
+            last_col,
+            &ConditionalFormatFormula::new()
+                .set_rule(rule.as_str())
+                .set_format(*format)
+                .set_stop_if_true(std::ptr::eq(*format, &cancelled_format)),
+        )?;
+    }
+
+    let rows: BTreeSet<u32> = data
+        .iter()
+        .enumerate()
+        .filter(|(_, employee)| anomalies.contains(&employee.wiersz))
+        .map(|(idx, _)| idx as u32 + 1)
+        .collect();
+    if let Some(&first) = rows.first() {
+        let ranges: Vec<String> = rows.iter().map(|row| cell_range(*row, 0, *row, last_col)).collect();
+        sheet.add_conditional_format(
+            first,
+            0,
+            first,
+            last_col,
+            &ConditionalFormatFormula::new()
+                .set_rule("=TRUE")
+                .set_format(&anomaly_format)
+                .set_multi_range(ranges.join(" ")),
+        )?;
+    }
+    Ok(())
+}
+
+// Nagłówki układu i wartości tekstowe (daty DD.MM.RRRR), wiersze zakończone CRLF
+pub struct CsvWriter {
+    pub options: CsvOptions,
+}
+
+impl CsvWriter {
+    fn field(&self, value: &str) -> String {
+        if value.contains(self.options.delimiter) || value.contains(['"', '\r', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    pub fn line(&self, values: &[String]) -> String {
+        let fields: Vec<String> = values.iter().map(|value| self.field(value)).collect();
+        fields.join(&self.options.delimiter.to_string()) + "\r\n"
+    }
+}
+
+impl ReportWriter for CsvWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let mut text = self.line(&report.layout.headers());
+        for employee in report.rows {
+            let values: Vec<String> = report
+                .layout
+                .kolumny
+                .iter()
+                .map(|column| layout::text_value(employee, &column.pole))
+                .collect();
+            text.push_str(&self.line(&values));
+        }
+
+        Ok(encode(&text, self.options.encoding))
+    }
+}
+
+pub fn encode(text: &str, encoding: CsvEncoding) -> Vec<u8> {
+    match encoding {
+        CsvEncoding::Utf8 => text.as_bytes().to_vec(),
+        CsvEncoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+        CsvEncoding::Windows1250 => encode_windows_1250(text),
+    }
+}
+
+// Znaki spoza strony kodowej (np. z innych alfabetów) są zastępowane znakiem "?"
+fn encode_windows_1250(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut buffer = [0u8; 4];
+    for c in text.chars() {
+        let (encoded, _, unmappable) = encoding_rs::WINDOWS_1250.encode(c.encode_utf8(&mut buffer));
+        if unmappable {
+            bytes.push(b'?');
+        } else {
+            bytes.extend_from_slice(&encoded);
+        }
+    }
+    bytes
+}
+
+// Wiersze jako obiekty z kluczami pól układu: daty RRRR-MM-DD, liczba dni jako liczba,
+// "Na opiekę" i "Pobyt w szpitalu" jako wartości logiczne
+pub struct JsonWriter {
+    pub lines: bool,
+}
+
+fn json_value(employee: &EmployeeData, field: &Field) -> Value {
+    match field {
+        Field::DataWystawienia | Field::DataOd | Field::DataDo => layout::date_value(employee, field)
+            .and_then(excel_to_date)
+            .map(|date| Value::from(date.format("%Y-%m-%d").to_string()))
+            .unwrap_or(Value::Null),
+        Field::Dni => layout::days(employee).map(Value::from).unwrap_or(Value::Null),
+        Field::NaOpieke | Field::PobytWSzpitalu => Value::from(is_yes(&layout::text_value(employee, field))),
+        _ => Value::from(layout::text_value(employee, field)),
+    }
+}
+
+fn json_row(layout: &ReportLayout, employee: &EmployeeData) -> Value {
+    let row: Map<String, Value> = layout
+        .kolumny
+        .iter()
+        .map(|column| (column.pole.key(), json_value(employee, &column.pole)))
+        .collect();
+    Value::Object(row)
+}
+
+impl ReportWriter for JsonWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let rows = report.rows.iter().map(|employee| json_row(report.layout, employee));
+        if self.lines {
+            let mut text = String::new();
+            for row in rows {
+                text.push_str(&serde_json::to_string(&row)?);
+                text.push('\n');
+            }
+            return Ok(text.into_bytes());
+        }
+
+        let document = json!({
+            "wygenerowano": report.started.to_rfc3339(),
+            "okres_od": report.period.0.format("%Y-%m-%d").to_string(),
+            "okres_do": report.period.1.format("%Y-%m-%d").to_string(),
+            "uklad": report.layout.nazwa,
+            "dane_osobowe": report.privacy.key(),
+            "liczba_wierszy": report.rows.len(),
+            "wiersze": rows.collect::<Vec<Value>>(),
+        });
+        Ok(serde_json::to_vec_pretty(&document)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(delimiter: char) -> CsvWriter {
+        CsvWriter { options: CsvOptions { delimiter, encoding: CsvEncoding::Utf8 } }
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        let values = ["Kowalski".to_string(), "Nowak; Anna".to_string(), "85010112345".to_string()];
+        assert_eq!(writer(';').line(&values), "Kowalski;\"Nowak; Anna\";85010112345\r\n");
+        // Średnik nie wymaga cudzysłowu przy innym separatorze
+        assert_eq!(writer(',').line(&values), "Kowalski,Nowak; Anna,85010112345\r\n");
+    }
+
+    #[test]
+    fn csv_quotes_and_line_breaks_are_escaped() {
+        let values = ["powiedział \"tak\"".to_string(), "linia 1\nlinia 2".to_string(), "a\r\nb".to_string()];
+        assert_eq!(
+            writer(';').line(&values),
+            "\"powiedział \"\"tak\"\"\";\"linia 1\nlinia 2\";\"a\r\nb\"\r\n"
+        );
+    }
+
+    #[test]
+    fn windows_1250_encoding() {
+        assert_eq!(encode("Łódź", CsvEncoding::Windows1250), vec![0xA3, 0xF3, 0x64, 0x9F]);
+        assert_eq!(encode("Ж", CsvEncoding::Windows1250), b"?".to_vec());
+        assert_eq!(encode("a", CsvEncoding::Utf8Bom), vec![0xEF, 0xBB, 0xBF, b'a']);
+    }
+}
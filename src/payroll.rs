@@ -0,0 +1,333 @@
+use anyhow::{bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::config;
+use crate::excel_merger::{is_yes, EmployeeData};
+use crate::layout;
+use crate::output::{self, CsvEncoding, CsvOptions, CsvWriter};
+
+const PROFILES_FILE: &str = "profile_placowe.json";
+pub const DEFAULT_PROFILE: &str = "CSV";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayrollField {
+    NumerPracownika,
+    KodNieobecnosci,
+    DataOd,
+    DataDo,
+    Dni,
+    ProcentWynagrodzenia,
+    NrZaswiadczenia,
+    Pesel,
+    Nazwisko,
+    Imie,
+}
+
+impl PayrollField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PayrollField::NumerPracownika => "Nr ewidencyjny",
+            PayrollField::KodNieobecnosci => "Kod nieobecności",
+            PayrollField::DataOd => "Data od",
+            PayrollField::DataDo => "Data do",
+            PayrollField::Dni => "Dni",
+            PayrollField::ProcentWynagrodzenia => "Procent wynagrodzenia",
+            PayrollField::NrZaswiadczenia => "Seria i nr zaśw.",
+            PayrollField::Pesel => "PESEL",
+            PayrollField::Nazwisko => "Nazwisko",
+            PayrollField::Imie => "Imię",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayrollColumn {
+    pub pole: PayrollField,
+    // Nagłówek w pliku z separatorem (pusty - nazwa pola)
+    #[serde(default)]
+    pub naglowek: String,
+    // Liczba znaków pola w pliku o stałej szerokości
+    #[serde(default)]
+    pub szerokosc: usize,
+    // Wyrównanie do prawej (np. dla liczb) w pliku o stałej szerokości
+    #[serde(default)]
+    pub do_prawej: bool,
+}
+
+impl PayrollColumn {
+    fn new(pole: PayrollField, szerokosc: usize, do_prawej: bool) -> Self {
+        Self { pole, naglowek: String::new(), szerokosc, do_prawej }
+    }
+
+    fn header(&self) -> String {
+        if self.naglowek.trim().is_empty() {
+            self.pole.label().to_string()
+        } else {
+            self.naglowek.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbsenceCode {
+    pub kod: String,
+    // Procent podstawy wynagrodzenia wypłacany za dni nieobecności
+    pub procent: u32,
+}
+
+impl AbsenceCode {
+    fn new(kod: &str, procent: u32) -> Self {
+        Self { kod: kod.to_string(), procent }
+    }
+}
+
+// Kody nieobecności programu płacowego dla rodzajów zaświadczeń L4
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AbsenceCodes {
+    pub choroba: AbsenceCode,
+    pub szpital: AbsenceCode,
+    pub opieka: AbsenceCode,
+}
+
+impl Default for AbsenceCodes {
+    fn default() -> Self {
+        Self {
+            choroba: AbsenceCode::new("CH", 80),
+            szpital: AbsenceCode::new("CHS", 70),
+            opieka: AbsenceCode::new("OP", 80),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayrollProfile {
+    pub nazwa: String,
+    // Nagłówek kolumny z numerem ewidencyjnym pracownika na liście pracowników
+    pub kolumna_numeru: String,
+    // Separator pól; None - pola o stałej szerokości
+    #[serde(default)]
+    pub separator: Option<char>,
+    // Pierwszy wiersz z nagłówkami (tylko plik z separatorem)
+    #[serde(default)]
+    pub naglowki: bool,
+    // Format daty, np. "%Y-%m-%d" albo "%d.%m.%Y"
+    pub format_daty: String,
+    // utf8, utf8-bom lub cp1250
+    pub kodowanie: String,
+    pub kolumny: Vec<PayrollColumn>,
+    #[serde(default)]
+    pub kody: AbsenceCodes,
+}
+
+impl PayrollProfile {
+    pub fn validate(&self) -> Result<()> {
+        if self.nazwa.trim().is_empty() {
+            bail!("Profil płacowy musi mieć nazwę");
+        }
+        if self.kolumny.is_empty() {
+            bail!("Profil płacowy {} nie zawiera żadnej kolumny", self.nazwa);
+        }
+        if self.separator.is_none() {
+            if let Some(column) = self.kolumny.iter().find(|column| column.szerokosc == 0) {
+                bail!("Kolumna {} w profilu {} nie ma szerokości pola", column.header(), self.nazwa);
+            }
+        }
+        if StrftimeItems::new(&self.format_daty).any(|item| matches!(item, Item::Error)) {
+            bail!("Niepoprawny format daty {} w profilu {}", self.format_daty, self.nazwa);
+        }
+        CsvEncoding::parse(&self.kodowanie)?;
+        Ok(())
+    }
+
+    // Rozszerzenie pliku wynikowego: csv dla pliku z separatorem, txt dla stałej szerokości
+    pub fn extension(&self) -> &'static str {
+        if self.separator.is_some() {
+            "csv"
+        } else {
+            "txt"
+        }
+    }
+
+    // Opieka ma pierwszeństwo przed pobytem w szpitalu, pozostałe zaświadczenia to choroba własna
+    pub fn code(&self, employee: &EmployeeData) -> &AbsenceCode {
+        if employee.is_care() {
+            &self.kody.opieka
+        } else if is_yes(&employee.pobyt_w_szpitalu) {
+            &self.kody.szpital
+        } else {
+            &self.kody.choroba
+        }
+    }
+
+    // Numer ewidencyjny pracownika z kolumny profilu na liście pracowników
+    pub fn employee_number<'a>(&self, employee: &'a EmployeeData) -> &'a str {
+        employee.dodatkowa(&self.kolumna_numeru).unwrap_or_default().trim()
+    }
+
+    fn value(&self, employee: &EmployeeData, field: PayrollField) -> String {
+        let date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format(&self.format_daty).to_string()).unwrap_or_default()
+        };
+        match field {
+            PayrollField::NumerPracownika => self.employee_number(employee).to_string(),
+            PayrollField::KodNieobecnosci => self.code(employee).kod.clone(),
+            PayrollField::DataOd => date(employee.okres().map(|(start, _)| start)),
+            PayrollField::DataDo => date(employee.okres().map(|(_, end)| end)),
+            PayrollField::Dni => layout::days(employee).map(|days| days.to_string()).unwrap_or_default(),
+            PayrollField::ProcentWynagrodzenia => self.code(employee).procent.to_string(),
+            PayrollField::NrZaswiadczenia => employee.nr_zaswiadczenia.clone(),
+            PayrollField::Pesel => employee.pesel.clone(),
+            PayrollField::Nazwisko => employee.nazwisko.clone(),
+            PayrollField::Imie => employee.imie.clone(),
+        }
+    }
+}
+
+// Plik dla programu płacowego zapisywany obok raportu
+#[derive(Debug, Clone)]
+pub struct PayrollOptions {
+    pub profile: PayrollProfile,
+    pub file: PathBuf,
+}
+
+// Zaświadczenia przenoszone do programu płacowego: nieanulowane, z poprawnym okresem
+pub fn records(data: &[EmployeeData]) -> Vec<&EmployeeData> {
+    data.iter()
+        .filter(|employee| !employee.is_cancelled() && layout::days(employee).is_some())
+        .collect()
+}
+
+// Wartość pola dopełniona spacjami do szerokości kolumny; dłuższa wartość jest błędem,
+// bo przycięta (np. numer ewidencyjny) trafiłaby do programu płacowego jako inna
+fn fixed(value: &str, column: &PayrollColumn, employee: &EmployeeData) -> Result<String> {
+    let length = value.chars().count();
+    if length > column.szerokosc {
+        bail!(
+            "Wartość pola {} w wierszu {} pliku L4 ma {} znaków, a szerokość pola w profilu płacowym to {}",
+            column.header(),
+            employee.wiersz,
+            length,
+            column.szerokosc
+        );
+    }
+    Ok(if column.do_prawej {
+        format!("{:>width$}", value, width = column.szerokosc)
+    } else {
+        format!("{:<width$}", value, width = column.szerokosc)
+    })
+}
+
+pub fn write(profile: &PayrollProfile, data: &[EmployeeData]) -> Result<Vec<u8>> {
+    profile.validate()?;
+    let encoding = CsvEncoding::parse(&profile.kodowanie)?;
+    let mut text = String::new();
+    match profile.separator {
+        Some(delimiter) => {
+            let writer = CsvWriter { options: CsvOptions { delimiter, encoding } };
+            if profile.naglowki {
+                let headers: Vec<String> = profile.kolumny.iter().map(PayrollColumn::header).collect();
+                text.push_str(&writer.line(&headers));
+            }
+            for employee in records(data) {
+                let values: Vec<String> = profile
+                    .kolumny
+                    .iter()
+                    .map(|column| profile.value(employee, column.pole))
+                    .collect();
+                text.push_str(&writer.line(&values));
+            }
+        }
+        None => {
+            for employee in records(data) {
+                for column in &profile.kolumny {
+                    text.push_str(&fixed(&profile.value(employee, column.pole), column, employee)?);
+                }
+                text.push_str("\r\n");
+            }
+        }
+    }
+    Ok(output::encode(&text, encoding))
+}
+
+pub fn built_in() -> Vec<PayrollProfile> {
+    vec![
+        PayrollProfile {
+            nazwa: DEFAULT_PROFILE.to_string(),
+            kolumna_numeru: "Nr ewidencyjny".to_string(),
+            separator: Some(';'),
+            naglowki: true,
+            format_daty: "%Y-%m-%d".to_string(),
+            kodowanie: "cp1250".to_string(),
+            kolumny: [
+                PayrollField::NumerPracownika,
+                PayrollField::KodNieobecnosci,
+                PayrollField::DataOd,
+                PayrollField::DataDo,
+                PayrollField::Dni,
+                PayrollField::ProcentWynagrodzenia,
+                PayrollField::NrZaswiadczenia,
+            ]
+            .into_iter()
+            .map(|field| PayrollColumn::new(field, 0, false))
+            .collect(),
+            kody: AbsenceCodes::default(),
+        },
+        PayrollProfile {
+            nazwa: "Stała szerokość".to_string(),
+            kolumna_numeru: "Nr ewidencyjny".to_string(),
+            separator: None,
+            naglowki: false,
+            format_daty: "%Y%m%d".to_string(),
+            kodowanie: "cp1250".to_string(),
+            kolumny: vec![
+                PayrollColumn::new(PayrollField::NumerPracownika, 10, false),
+                PayrollColumn::new(PayrollField::KodNieobecnosci, 4, false),
+                PayrollColumn::new(PayrollField::DataOd, 8, false),
+                PayrollColumn::new(PayrollField::DataDo, 8, false),
+                PayrollColumn::new(PayrollField::Dni, 3, true),
+                PayrollColumn::new(PayrollField::ProcentWynagrodzenia, 3, true),
+                PayrollColumn::new(PayrollField::NrZaswiadczenia, 12, false),
+            ],
+            kody: AbsenceCodes::default(),
+        },
+    ]
+}
+
+// Profile wbudowane i zapisane przez użytkownika; zapisany profil o tej samej nazwie zastępuje wbudowany
+pub fn all() -> Result<Vec<PayrollProfile>> {
+    let saved: Vec<PayrollProfile> = config::load(PROFILES_FILE)?;
+    let mut profiles = built_in();
+    for profile in saved {
+        match profiles.iter_mut().find(|existing| existing.nazwa == profile.nazwa) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+    Ok(profiles)
+}
+
+pub fn find(name: &str) -> Result<PayrollProfile> {
+    let profiles = all()?;
+    let names: Vec<&str> = profiles.iter().map(|profile| profile.nazwa.as_str()).collect();
+    match profiles.iter().find(|profile| profile.nazwa.to_lowercase() == name.trim().to_lowercase()) {
+        Some(profile) => Ok(profile.clone()),
+        None => bail!("Nieznany profil płacowy: {} (dostępne: {})", name, names.join(", ")),
+    }
+}
+
+// Zapisuje wszystkie profile do pliku ustawień, aby można je było edytować
+pub fn save_all() -> Result<PathBuf> {
+    let profiles = all()?;
+    for profile in &profiles {
+        profile.validate()?;
+    }
+    config::save(PROFILES_FILE, &profiles)
+}
+
+pub fn profiles_file() -> PathBuf {
+    config::config_dir().join(PROFILES_FILE)
+}
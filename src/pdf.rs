@@ -0,0 +1,247 @@
+use ab_glyph::{Font, FontRef};
+use anyhow::{anyhow, Context, Result};
+use printpdf::{Color, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point, Rect, Rgb};
+use std::io::Cursor;
+
+use crate::layout;
+use crate::output::{Report, ReportWriter};
+
+// Czcionka wbudowana w egui zawiera polskie znaki, więc raport nie wymaga czcionek systemowych
+const FONT_NAME: &str = "Ubuntu-Light";
+
+// A4 poziomo, wymiary w milimetrach
+const PAGE_WIDTH: f32 = 297.0;
+const PAGE_HEIGHT: f32 = 210.0;
+const MARGIN: f32 = 15.0;
+const FOOTER_HEIGHT: f32 = 10.0;
+const ROW_HEIGHT: f32 = 5.5;
+const CELL_PADDING: f32 = 1.5;
+const MAX_COLUMN_WIDTH: f32 = 60.0;
+
+const TITLE_SIZE: f32 = 14.0;
+const INFO_SIZE: f32 = 9.0;
+const TABLE_SIZE: f32 = 8.0;
+const PT_TO_MM: f32 = 25.4 / 72.0;
+
+pub struct PdfWriter {
+    // Nazwa firmy w nagłówku raportu (pusta - pomijana)
+    pub company: String,
+}
+
+struct Measure<'a> {
+    font: FontRef<'a>,
+    units_per_em: f32,
+}
+
+impl Measure<'_> {
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let units: f32 = text
+            .chars()
+            .map(|c| self.font.h_advance_unscaled(self.font.glyph_id(c)))
+            .sum();
+        units / self.units_per_em * size * PT_TO_MM
+    }
+
+    // Tekst skrócony tak, aby zmieścił się w kolumnie
+    fn fit(&self, text: &str, size: f32, width: f32) -> String {
+        if self.width(text, size) <= width {
+            return text.to_string();
+        }
+        let mut fitted: String = text.to_string();
+        while !fitted.is_empty() && self.width(&format!("{}…", fitted), size) > width {
+            fitted.pop();
+        }
+        format!("{}…", fitted)
+    }
+}
+
+// Szerokości kolumn według najdłuższej wartości, dopasowane do szerokości strony
+fn column_widths(measure: &Measure, headers: &[String], rows: &[Vec<String>]) -> Vec<f32> {
+    let mut widths: Vec<f32> = headers
+        .iter()
+        .enumerate()
+        .map(|(col, header)| {
+            rows.iter()
+                .map(|row| measure.width(&row[col], TABLE_SIZE))
+                .fold(measure.width(header, TABLE_SIZE), f32::max)
+                .min(MAX_COLUMN_WIDTH)
+                + 2.0 * CELL_PADDING
+        })
+        .collect();
+    let total: f32 = widths.iter().sum();
+    let available = PAGE_WIDTH - 2.0 * MARGIN;
+    for width in &mut widths {
+        *width *= available / total;
+    }
+    widths
+}
+
+fn rgb(r: f32, g: f32, b: f32) -> Color {
+    Color::Rgb(Rgb::new(r, g, b, None))
+}
+
+// Współrzędna y w PDF liczona jest od dołu strony
+fn text(layer: &PdfLayerReference, font: &IndirectFontRef, value: &str, size: f32, x: f32, top: f32) {
+    layer.use_text(value, size, Mm(x), Mm(PAGE_HEIGHT - top), font);
+}
+
+fn horizontal_line(layer: &PdfLayerReference, top: f32) {
+    let y = Mm(PAGE_HEIGHT - top);
+    layer.add_line(Line {
+        points: vec![(Point::new(Mm(MARGIN), y), false), (Point::new(Mm(PAGE_WIDTH - MARGIN), y), false)],
+        is_closed: false,
+    });
+}
+
+impl ReportWriter for PdfWriter {
+    fn write(&self, report: &Report) -> Result<Vec<u8>> {
+        let font_data = egui::FontDefinitions::default()
+            .font_data
+            .get(FONT_NAME)
+            .map(|data| data.font.to_vec())
+            .with_context(|| format!("Brak wbudowanej czcionki {}", FONT_NAME))?;
+        let font_ref = FontRef::try_from_slice(&font_data).map_err(|e| anyhow!("Niepoprawna czcionka: {}", e))?;
+        let measure = Measure { units_per_em: font_ref.units_per_em().unwrap_or(1000.0), font: font_ref };
+
+        let headers = report.layout.headers();
+        let rows: Vec<Vec<String>> = report
+            .rows
+            .iter()
+            .map(|employee| {
+                report
+                    .layout
+                    .kolumny
+                    .iter()
+                    .map(|column| layout::text_value(employee, &column.pole))
+                    .collect()
+            })
+            .collect();
+        let widths = column_widths(&measure, &headers, &rows);
+
+        let summary = report.summary;
+        let mut info = Vec::new();
+        if !self.company.trim().is_empty() {
+            info.push(format!("Firma: {}", self.company.trim()));
+        }
+        info.push(format!(
+            "Okres: {} - {}",
+            report.period.0.format("%d.%m.%Y"),
+            report.period.1.format("%d.%m.%Y")
+        ));
+        info.push(format!("Data wygenerowania: {}", report.started.format("%d.%m.%Y %H:%M")));
+        info.push(format!(
+            "Pracownicy na liście: {}    Wiersze L4: {}    Dopasowane zaświadczenia: {}    Osoby z zaświadczeniami: {}    Niedopasowane wiersze L4: {}",
+            summary.pracownicy, summary.wiersze_l4, summary.dopasowane, summary.osoby_dopasowane, summary.niedopasowane
+        ));
+        info.push(format!(
+            "Wiersze w raporcie: {}    Układ: {}    Dane osobowe: {}",
+            rows.len(),
+            report.layout.nazwa,
+            report.privacy.label()
+        ));
+
+        // Podział wierszy na strony - pierwsza strona zawiera nagłówek raportu
+        let header_height = 10.0 + info.len() as f32 * 5.0 + 4.0;
+        let table_bottom = PAGE_HEIGHT - MARGIN - FOOTER_HEIGHT;
+        let first_capacity = ((table_bottom - MARGIN - header_height - ROW_HEIGHT) / ROW_HEIGHT) as usize;
+        let capacity = ((table_bottom - MARGIN - ROW_HEIGHT) / ROW_HEIGHT) as usize;
+        let mut pages: Vec<&[Vec<String>]> = vec![&rows[..first_capacity.min(rows.len())]];
+        let mut rest = &rows[first_capacity.min(rows.len())..];
+        while !rest.is_empty() {
+            let (page, next) = rest.split_at(capacity.min(rest.len()));
+            pages.push(page);
+            rest = next;
+        }
+        // Miejsce na podpis pod tabelą; gdy go brakuje, podpis trafia na osobną stronę
+        let signature_height = 20.0;
+        let last_rows = pages.last().map_or(0, |page| page.len()) as f32;
+        let last_top = if pages.len() == 1 { MARGIN + header_height } else { MARGIN };
+        let table_end = last_top + (last_rows + 1.0) * ROW_HEIGHT;
+        let signature_on_new_page = table_end + signature_height > table_bottom;
+        let page_count = pages.len() + usize::from(signature_on_new_page);
+
+        let (document, first_page, first_layer) =
+            PdfDocument::new("Raport L4", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Strona 1");
+        let font = document
+            .add_external_font(Cursor::new(font_data.clone()))
+            .map_err(|e| anyhow!("Nie można osadzić czcionki: {}", e))?;
+
+        let mut layers = vec![document.get_page(first_page).get_layer(first_layer)];
+        for number in 2..=page_count {
+            let (page, layer) = document.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), format!("Strona {}", number));
+            layers.push(document.get_page(page).get_layer(layer));
+        }
+
+        for (index, (layer, page_rows)) in layers.iter().zip(&pages).enumerate() {
+            let mut top = MARGIN;
+            if index == 0 {
+                text(layer, &font, "Raport zwolnień lekarskich (L4)", TITLE_SIZE, MARGIN, MARGIN + 5.0);
+                for (line, value) in info.iter().enumerate() {
+                    text(layer, &font, value, INFO_SIZE, MARGIN, MARGIN + 12.0 + line as f32 * 5.0);
+                }
+                top += header_height;
+            }
+
+            // Nagłówek tabeli powtarzany na każdej stronie
+            layer.set_fill_color(rgb(0.31, 0.51, 0.74));
+            layer.add_rect(Rect::new(
+                Mm(MARGIN),
+                Mm(PAGE_HEIGHT - top - ROW_HEIGHT),
+                Mm(PAGE_WIDTH - MARGIN),
+                Mm(PAGE_HEIGHT - top),
+            ));
+            layer.set_fill_color(rgb(1.0, 1.0, 1.0));
+            let mut x = MARGIN;
+            for (header, width) in headers.iter().zip(&widths) {
+                let value = measure.fit(header, TABLE_SIZE, width - 2.0 * CELL_PADDING);
+                text(layer, &font, &value, TABLE_SIZE, x + CELL_PADDING, top + ROW_HEIGHT - 1.7);
+                x += width;
+            }
+            top += ROW_HEIGHT;
+
+            layer.set_fill_color(rgb(0.0, 0.0, 0.0));
+            layer.set_outline_color(rgb(0.8, 0.8, 0.8));
+            layer.set_outline_thickness(0.3);
+            for row in page_rows.iter() {
+                let mut x = MARGIN;
+                for (value, width) in row.iter().zip(&widths) {
+                    let value = measure.fit(value, TABLE_SIZE, width - 2.0 * CELL_PADDING);
+                    text(layer, &font, &value, TABLE_SIZE, x + CELL_PADDING, top + ROW_HEIGHT - 1.7);
+                    x += width;
+                }
+                top += ROW_HEIGHT;
+                horizontal_line(layer, top);
+            }
+        }
+
+        if let Some(layer) = layers.last() {
+            let signature_top = if signature_on_new_page { MARGIN + 10.0 } else { table_end + 12.0 };
+            text(
+                layer,
+                &font,
+                "Sporządził(a): ..............................................        Data i podpis: ..............................................",
+                INFO_SIZE,
+                MARGIN,
+                signature_top,
+            );
+        }
+
+        for (index, layer) in layers.iter().enumerate() {
+            layer.set_fill_color(rgb(0.4, 0.4, 0.4));
+            let footer_top = PAGE_HEIGHT - MARGIN;
+            let left = if self.company.trim().is_empty() {
+                format!("Raport L4 z dnia {}", report.started.format("%d.%m.%Y"))
+            } else {
+                format!("{} - raport L4 z dnia {}", self.company.trim(), report.started.format("%d.%m.%Y"))
+            };
+            text(layer, &font, &left, TABLE_SIZE, MARGIN, footer_top);
+            let number = format!("Strona {} z {}", index + 1, page_count);
+            let x = PAGE_WIDTH - MARGIN - measure.width(&number, TABLE_SIZE);
+            text(layer, &font, &number, TABLE_SIZE, x, footer_top);
+        }
+
+        document
+            .save_to_bytes()
+            .map_err(|e| anyhow!("Nie można zapisać pliku PDF: {}", e))
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::config;
+use crate::excel_merger::EmployeeData;
+
+// Klucz pseudonimów: zmienna L4_KLUCZ_PSEUDONIMOW albo plik w katalogu ustawień tworzony przy pierwszym użyciu
+const KEY_FILE: &str = "klucz_pseudonimow.txt";
+const KEY_VARIABLE: &str = "L4_KLUCZ_PSEUDONIMOW";
+// Liczba widocznych znaków maskowanego numeru PESEL (data urodzenia)
+const VISIBLE_DIGITS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrivacyMode {
+    // Pełne dane: PESEL, nazwisko i imię
+    #[default]
+    Pelny,
+    // PESEL z widoczną datą urodzenia, np. 850101*****
+    Maskowany,
+    // Pseudonim z klucza zamiast numeru PESEL, bez nazwiska i imienia
+    Pseudonim,
+    // Tylko zestawienia zbiorcze, bez danych poszczególnych osób
+    Zanonimizowany,
+}
+
+impl PrivacyMode {
+    pub const ALL: [PrivacyMode; 4] = [
+        PrivacyMode::Pelny,
+        PrivacyMode::Maskowany,
+        PrivacyMode::Pseudonim,
+        PrivacyMode::Zanonimizowany,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match PrivacyMode::ALL.iter().find(|mode| mode.key() == name.trim().to_lowercase()) {
+            Some(mode) => Ok(*mode),
+            None => bail!("Nieznany tryb danych osobowych: {} (dostępne: pelny, maskowany, pseudonim, zanonimizowany)", name),
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            PrivacyMode::Pelny => "pelny",
+            PrivacyMode::Maskowany => "maskowany",
+            PrivacyMode::Pseudonim => "pseudonim",
+            PrivacyMode::Zanonimizowany => "zanonimizowany",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PrivacyMode::Pelny => "pełne dane",
+            PrivacyMode::Maskowany => "PESEL maskowany",
+            PrivacyMode::Pseudonim => "pseudonimy",
+            PrivacyMode::Zanonimizowany => "tylko zestawienia zbiorcze",
+        }
+    }
+
+    // Czy raport może zawierać wiersze dotyczące poszczególnych osób
+    pub fn has_rows(&self) -> bool {
+        *self != PrivacyMode::Zanonimizowany
+    }
+}
+
+// Zamiana danych identyfikujących przy zapisie raportu; obliczenia korzystają z pełnych danych
+#[derive(Debug, Clone, Default)]
+pub struct Privacy {
+    pub mode: PrivacyMode,
+    key: Vec<u8>,
+}
+
+impl Privacy {
+    pub fn new(mode: PrivacyMode) -> Result<Self> {
+        let key = if mode == PrivacyMode::Pseudonim { load_key()? } else { Vec::new() };
+        Ok(Self { mode, key })
+    }
+
+    pub fn pesel(&self, pesel: &str) -> String {
+        match self.mode {
+            PrivacyMode::Pelny => pesel.to_string(),
+            PrivacyMode::Maskowany => pesel
+                .trim()
+                .chars()
+                .enumerate()
+                .map(|(idx, c)| if idx < VISIBLE_DIGITS { c } else { '*' })
+                .collect(),
+            PrivacyMode::Pseudonim => self.pseudonym(pesel),
+            PrivacyMode::Zanonimizowany => String::new(),
+        }
+    }
+
+    pub fn name(&self, name: &str) -> String {
+        match self.mode {
+            PrivacyMode::Pelny | PrivacyMode::Maskowany => name.to_string(),
+            PrivacyMode::Pseudonim | PrivacyMode::Zanonimizowany => String::new(),
+        }
+    }
+
+    // Ten sam PESEL i klucz dają zawsze ten sam pseudonim, więc raporty z kolejnych miesięcy można zestawiać
+    pub fn pseudonym(&self, pesel: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC przyjmuje klucz dowolnej długości");
+        mac.update(pesel.trim().as_bytes());
+        let hash = mac.finalize().into_bytes();
+        let hex: String = hash.iter().take(6).map(|byte| format!("{:02X}", byte)).collect();
+        format!("P-{}", hex)
+    }
+
+    // Kopie wierszy do zapisu w raporcie; w trybie zanonimizowanym raport nie zawiera wierszy
+    pub fn apply(&self, data: &[EmployeeData]) -> Vec<EmployeeData> {
+        if !self.mode.has_rows() {
+            return Vec::new();
+        }
+        data.iter()
+            .map(|employee| EmployeeData {
+                pesel: self.pesel(&employee.pesel),
+                nazwisko: self.name(&employee.nazwisko),
+                imie: self.name(&employee.imie),
+                ..employee.clone()
+            })
+            .collect()
+    }
+}
+
+fn load_key() -> Result<Vec<u8>> {
+    if let Some(key) = std::env::var(KEY_VARIABLE).ok().filter(|key| !key.trim().is_empty()) {
+        return Ok(key.trim().as_bytes().to_vec());
+    }
+    let path = config::config_dir().join(KEY_FILE);
+    if path.exists() {
+        let key = std::fs::read_to_string(&path)
+            .with_context(|| format!("Nie można odczytać klucza pseudonimów {}", path.display()))?;
+        if key.trim().is_empty() {
+            bail!("Plik klucza pseudonimów {} jest pusty", path.display());
+        }
+        return Ok(key.trim().as_bytes().to_vec());
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("Nie można wygenerować klucza pseudonimów: {}", e))?;
+    let key: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let dir = config::config_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("Nie można utworzyć katalogu {}", dir.display()))?;
+    std::fs::write(&path, &key).with_context(|| format!("Nie można zapisać klucza pseudonimów {}", path.display()))?;
+    Ok(key.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privacy(mode: PrivacyMode, key: &[u8]) -> Privacy {
+        Privacy { mode, key: key.to_vec() }
+    }
+
+    #[test]
+    fn masked_pesel_shows_only_the_birth_date() {
+        let masked = privacy(PrivacyMode::Maskowany, b"");
+        assert_eq!(masked.pesel("85010112345"), "850101*****");
+        assert_eq!(masked.pesel(" 85010112345 "), "850101*****");
+        assert_eq!(masked.name("Kowalski"), "Kowalski");
+    }
+
+    #[test]
+    fn pseudonym_is_stable_for_the_same_key() {
+        let first = privacy(PrivacyMode::Pseudonim, b"klucz");
+        let second = privacy(PrivacyMode::Pseudonim, b"klucz");
+        let pseudonym = first.pesel("85010112345");
+        assert!(pseudonym.starts_with("P-") && pseudonym.len() == 14, "{}", pseudonym);
+        assert_eq!(second.pesel("85010112345"), pseudonym);
+        assert_eq!(first.pesel(" 85010112345"), pseudonym);
+        assert_eq!(first.name("Kowalski"), "");
+    }
+
+    #[test]
+    fn pseudonym_depends_on_pesel_and_key() {
+        let privacy_a = privacy(PrivacyMode::Pseudonim, b"klucz");
+        let privacy_b = privacy(PrivacyMode::Pseudonim, b"inny klucz");
+        assert_ne!(privacy_a.pesel("85010112345"), privacy_a.pesel("85010112346"));
+        assert_ne!(privacy_a.pesel("85010112345"), privacy_b.pesel("85010112345"));
+    }
+
+    #[test]
+    fn apply_keeps_certificate_data() {
+        let employee = EmployeeData {
+            pesel: "85010112345".to_string(),
+            nazwisko: "Kowalski".to_string(),
+            imie: "Jan".to_string(),
+            nr_zaswiadczenia: "ZUS1".to_string(),
+            wiersz: 7,
+            ..Default::default()
+        };
+        let rows = privacy(PrivacyMode::Pseudonim, b"klucz").apply(&[employee]);
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].pesel.starts_with("P-"));
+        assert_eq!((rows[0].nazwisko.as_str(), rows[0].imie.as_str()), ("", ""));
+        assert_eq!((rows[0].nr_zaswiadczenia.as_str(), rows[0].wiersz), ("ZUS1", 7));
+        assert!(privacy(PrivacyMode::Zanonimizowany, b"").apply(&rows).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Color, Format, Workbook};
+
+use crate::episodes;
+use crate::excel_merger::{date_to_excel, EmployeeData};
+use crate::privacy::Privacy;
+
+// Po chorobie trwającej dłużej niż 30 dni pracownik musi przejść kontrolne badanie lekarskie
+// (art. 229 § 2 KP); dni opieki nad dzieckiem lub członkiem rodziny się nie liczą
+pub const CONTROL_EXAM_THRESHOLD_DAYS: i64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct ReturnEntry {
+    pub nazwisko: String,
+    pub imie: String,
+    pub pesel: String,
+    pub nieobecnosc_od: NaiveDate,
+    pub nieobecnosc_do: NaiveDate,
+    pub data_powrotu: NaiveDate,
+    pub dni_nieobecnosci: i64,
+    // Dni nieobecności z powodu własnej choroby
+    pub dni_choroby: i64,
+    pub badanie_kontrolne: bool,
+}
+
+// Wybiera ciągłe nieobecności (epizody bez przerw), które kończą się w oknie `window_days` dni
+// od `today`. Trwające nieobecności z chorobą dłuższą niż 30 dni są zwracane niezależnie od okna,
+// żeby można było wcześniej umówić badanie.
+pub fn build_return_list(data: &[EmployeeData], today: NaiveDate, window_days: u32) -> Vec<ReturnEntry> {
+    let window_end = today + Duration::days(window_days as i64);
+    let mut entries = Vec::new();
+
+    for episode in episodes::build_episodes(data, 0) {
+        let data_powrotu = episode.end + Duration::days(1);
+        let dni_nieobecnosci = episode.dni();
+        let dni_choroby = episode.dni_choroby();
+        let badanie_kontrolne = dni_choroby > CONTROL_EXAM_THRESHOLD_DAYS;
+        let in_window = data_powrotu >= today && data_powrotu <= window_end;
+        let ongoing = episode.start <= today && episode.end >= today;
+
+        if in_window || (ongoing && badanie_kontrolne) {
+            entries.push(ReturnEntry {
+                nazwisko: episode.employee.nazwisko.clone(),
+                imie: episode.employee.imie.clone(),
+                pesel: episode.employee.pesel.clone(),
+                nieobecnosc_od: episode.start,
+                nieobecnosc_do: episode.end,
+                data_powrotu,
+                dni_nieobecnosci,
+                dni_choroby,
+                badanie_kontrolne,
+            });
+        }
+    }
+
+    entries.sort_by(|a, b| a.data_powrotu.cmp(&b.data_powrotu).then_with(|| a.nazwisko.cmp(&b.nazwisko)));
+    entries
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    entries: &[ReturnEntry],
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Powroty do pracy")?;
+
+    let headers = [
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Nieobecność od",
+        "Nieobecność do",
+        "Data powrotu",
+        "Dni nieobecności",
+        "Dni choroby",
+        "Badanie kontrolne",
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+    }
+
+    sheet.set_column_width(0, 20.0)?; // Nazwisko
+    sheet.set_column_width(1, 15.0)?; // Imię
+    sheet.set_column_width(2, 12.0)?; // PESEL
+    sheet.set_column_width(3, 14.0)?; // Nieobecność od
+    sheet.set_column_width(4, 14.0)?; // Nieobecność do
+    sheet.set_column_width(5, 12.0)?; // Data powrotu
+    sheet.set_column_width(6, 16.0)?; // Dni nieobecności
+    sheet.set_column_width(7, 12.0)?; // Dni choroby
+    sheet.set_column_width(8, 17.0)?; // Badanie kontrolne
+
+    let exam_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xFFC7CE))
+        .set_font_color(Color::RGB(0x9C0006));
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let row = idx as u32 + 1;
+        sheet.write_string(row, 0, privacy.name(&entry.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&entry.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&entry.pesel))?;
+        sheet.write_number_with_format(row, 3, date_to_excel(entry.nieobecnosc_od), date_format)?;
+        sheet.write_number_with_format(row, 4, date_to_excel(entry.nieobecnosc_do), date_format)?;
+        sheet.write_number_with_format(row, 5, date_to_excel(entry.data_powrotu), date_format)?;
+        sheet.write_number(row, 6, entry.dni_nieobecnosci as f64)?;
+        sheet.write_number(row, 7, entry.dni_choroby as f64)?;
+        if entry.badanie_kontrolne {
+            sheet.write_string_with_format(row, 8, "TAK", &exam_format)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn certificate(start: NaiveDate, end: NaiveDate, care: bool) -> EmployeeData {
+        EmployeeData {
+            nazwisko: "Kowalska".to_string(),
+            pesel: "85010112345".to_string(),
+            data_od: Some(date_to_excel(start)),
+            data_do: Some(date_to_excel(end)),
+            na_opieke: if care { "Tak" } else { "" }.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn care_days_do_not_count_towards_the_control_exam() {
+        let data = vec![
+            certificate(date(9, 1), date(9, 20), false),
+            certificate(date(9, 21), date(10, 10), true),
+        ];
+        let entries = build_return_list(&data, date(10, 5), 14);
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].dni_nieobecnosci, entries[0].dni_choroby), (40, 20));
+        assert!(!entries[0].badanie_kontrolne);
+    }
+
+    #[test]
+    fn illness_longer_than_30_days_requires_the_control_exam() {
+        let data = vec![
+            certificate(date(9, 1), date(9, 20), false),
+            certificate(date(9, 21), date(10, 1), false),
+        ];
+        let entries = build_return_list(&data, date(10, 1), 0);
+        assert_eq!(entries[0].dni_choroby, 31);
+        assert!(entries[0].badanie_kontrolne);
+        assert_eq!(entries[0].data_powrotu, date(10, 2));
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDate;
+use std::fmt;
+
+use crate::excel_merger::{is_yes, EmployeeData};
+
+#[derive(Debug, Clone)]
+pub struct AbsentEmployee {
+    pub nazwisko: String,
+    pub imie: String,
+    pub pesel: String,
+    pub data_od: NaiveDate,
+    pub data_do: NaiveDate,
+    // Liczba dni do końca zwolnienia, łącznie ze sprawdzanym dniem
+    pub pozostalo_dni: i64,
+    pub na_opieke: bool,
+    pub pobyt_w_szpitalu: bool,
+}
+
+impl fmt::Display for AbsentEmployee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}) - {} {} - {}, pozostało dni: {}",
+            self.nazwisko,
+            self.imie,
+            self.pesel,
+            if self.pobyt_w_szpitalu {
+                "szpital"
+            } else if self.na_opieke {
+                "opieka"
+            } else {
+                "choroba"
+            },
+            self.data_od.format("%d.%m.%Y"),
+            self.data_do.format("%d.%m.%Y"),
+            self.pozostalo_dni
+        )
+    }
+}
+
+pub fn absent_on(data: &[EmployeeData], date: NaiveDate) -> Vec<AbsentEmployee> {
+    let mut absent: Vec<AbsentEmployee> = data
+        .iter()
+        .filter(|employee| !employee.is_cancelled())
+        .filter_map(|employee| {
+            let (data_od, data_do) = employee.okres()?;
+            if data_od > date || data_do < date {
+                return None;
+            }
+            Some(AbsentEmployee {
+                nazwisko: employee.nazwisko.clone(),
+                imie: employee.imie.clone(),
+                pesel: employee.pesel.clone(),
+                data_od,
+                data_do,
+                pozostalo_dni: (data_do - date).num_days() + 1,
+                na_opieke: employee.is_care(),
+                pobyt_w_szpitalu: is_yes(&employee.pobyt_w_szpitalu),
+            })
+        })
+        .collect();
+
+    absent.sort_by(|a, b| a.nazwisko.cmp(&b.nazwisko).then_with(|| a.imie.cmp(&b.imie)));
+    absent
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use crate::excel_merger::EmployeeData;
+use crate::summary::{self, Summary};
+
+pub const DEFAULT_TEMPLATE: &str = "L4_{wartosc}.xlsx";
+const NO_VALUE: &str = "(brak)";
+
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    // Nagłówek kolumny z listy pracowników, według której dzielony jest raport
+    pub column: String,
+    // Szablon nazwy pliku: {wartosc} - wartość kolumny, {data} - dzisiejsza data (DD-MM-RRRR)
+    pub file_name_template: String,
+}
+
+pub struct SplitFile {
+    pub wartosc: String,
+    pub plik: PathBuf,
+    pub wiersze: usize,
+}
+
+// Wiersze L4 pogrupowane według wartości kolumny podziału
+pub fn group(data: &[EmployeeData], column: &str) -> BTreeMap<String, Vec<EmployeeData>> {
+    let mut groups: BTreeMap<String, Vec<EmployeeData>> = BTreeMap::new();
+    for employee in data {
+        let value = employee
+            .dodatkowa(column)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(NO_VALUE);
+        groups.entry(value.to_string()).or_default().push(employee.clone());
+    }
+    groups
+}
+
+// Podsumowanie każdej grupy: pracownicy z tą wartością kolumny i ich zaświadczenia. Liczone z pełnych
+// danych, bo po zamaskowaniu numerów PESEL różne osoby mogłyby wyglądać jak jedna.
+pub fn summaries(data: &[EmployeeData], employees: &[EmployeeData], column: &str) -> BTreeMap<String, Summary> {
+    let employees = group(employees, column);
+    group(data, column)
+        .into_iter()
+        .map(|(value, rows)| {
+            let pracownicy = employees.get(&value).map_or(0, Vec::len);
+            let summary = summary::from_rows(pracownicy, &rows, 0, 0);
+            (value, summary)
+        })
+        .collect()
+}
+
+// Nazwa pliku z szablonu z podanym rozszerzeniem, bez znaków niedozwolonych w Windows
+// i bez powtórzeń w obrębie podziału
+pub fn file_name(template: &str, value: &str, today: NaiveDate, extension: &str, used: &mut HashSet<String>) -> String {
+    let safe_value: String = value
+        .chars()
+        .map(|c| if "\\/:*?\"<>|".contains(c) || c.is_control() { '_' } else { c })
+        .collect();
+    let name = template
+        .replace("{wartosc}", safe_value.trim())
+        .replace("{data}", &today.format("%d-%m-%Y").to_string());
+    // Szablon domyślny kończy się na .xlsx także wtedy, gdy wynik jest w innym formacie
+    let stem = name
+        .strip_suffix(&format!(".{}", extension))
+        .or_else(|| name.strip_suffix(".xlsx"))
+        .unwrap_or(&name)
+        .to_string();
+
+    let mut name = format!("{}.{}", stem, extension);
+    let mut counter = 2;
+    while !used.insert(name.to_lowercase()) {
+        name = format!("{}_{}.{}", stem, counter, extension);
+        counter += 1;
+    }
+    name
+}
+
+// Skoroszyt indeksu: wartość kolumny podziału, nazwa pliku i liczba wierszy
+pub fn index_workbook(files: &[SplitFile], column: &str, header_format: &Format) -> Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Indeks")?;
+
+    let headers = [column, "Plik", "Liczba wierszy"];
+    let widths = [24.0, 40.0, 14.0];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, header_format)?;
+        sheet.set_column_width(col as u16, width)?;
+    }
+
+    for (idx, file) in files.iter().enumerate() {
+        let row = idx as u32 + 1;
+        let name = file.plik.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        sheet.write_string(row, 0, &file.wartosc)?;
+        sheet.write_string(row, 1, name)?;
+        sheet.write_number(row, 2, file.wiersze as f64)?;
+    }
+
+    let total_row = files.len() as u32 + 1;
+    let bold = Format::new().set_bold();
+    sheet.write_string_with_format(total_row, 1, "Razem", &bold)?;
+    sheet.write_number_with_format(
+        total_row,
+        2,
+        files.iter().map(|file| file.wiersze).sum::<usize>() as f64,
+        &bold,
+    )?;
+
+    Ok(workbook.save_to_buffer()?)
+}
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::calendar;
+use crate::excel_merger::{date_to_excel, EmployeeData};
+use crate::privacy::Privacy;
+
+const NO_DEPARTMENT: &str = "(brak działu)";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupStats {
+    pub nazwa: String,
+    pub pracownicy: usize,
+    // Dni robocze w okresie pomnożone przez liczbę pracowników
+    pub dni_dostepne: i64,
+    // Dni robocze objęte zwolnieniami
+    pub dni_absencji: i64,
+    pub wskaznik_absencji: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmployeeStats {
+    pub nazwisko: String,
+    pub imie: String,
+    pub pesel: String,
+    pub dzial: String,
+    // S - liczba odrębnych nieobecności
+    pub liczba_nieobecnosci: i64,
+    // D - łączna liczba dni roboczych nieobecności
+    pub dni_absencji: i64,
+    // S² × D
+    pub wspolczynnik_bradforda: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Statistics {
+    pub okres_od: NaiveDate,
+    pub okres_do: NaiveDate,
+    pub dni_robocze: i64,
+    pub firma: GroupStats,
+    pub dzialy: Vec<GroupStats>,
+    pub pracownicy: Vec<EmployeeStats>,
+}
+
+fn group_stats(nazwa: &str, pracownicy: usize, dni_absencji: i64, dni_robocze: i64) -> GroupStats {
+    let dni_dostepne = dni_robocze * pracownicy as i64;
+    GroupStats {
+        nazwa: nazwa.to_string(),
+        pracownicy,
+        dni_dostepne,
+        dni_absencji,
+        wskaznik_absencji: if dni_dostepne > 0 {
+            dni_absencji as f64 / dni_dostepne as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+pub fn department_name(dzial: &str) -> &str {
+    if dzial.is_empty() {
+        NO_DEPARTMENT
+    } else {
+        dzial
+    }
+}
+
+pub fn compute(
+    employees: &[EmployeeData],
+    matched: &[EmployeeData],
+    period_start: NaiveDate,
+    period_end: NaiveDate,
+) -> Statistics {
+    let dni_robocze = calendar::working_days(period_start, period_end);
+
+    // Dni nieobecności każdej osoby w okresie - zbiór, żeby nakładające się zaświadczenia nie liczyły się podwójnie
+    let mut absence_days: HashMap<&str, BTreeSet<NaiveDate>> = HashMap::new();
+    for employee in matched.iter().filter(|employee| !employee.is_cancelled()) {
+        let Some((start, end)) = employee.okres() else {
+            continue;
+        };
+        let start = start.max(period_start);
+        let end = end.min(period_end);
+        if start > end {
+            continue;
+        }
+        absence_days
+            .entry(&employee.pesel)
+            .or_default()
+            .extend(start.iter_days().take_while(|date| *date <= end));
+    }
+
+    let mut pracownicy = Vec::new();
+    let mut departments: BTreeMap<&str, (usize, i64)> = BTreeMap::new();
+    let mut company_days = 0;
+
+    for employee in employees {
+        let days = absence_days.get(employee.pesel.as_str());
+        let dni_absencji = days
+            .map(|days| days.iter().filter(|date| calendar::is_working_day(**date)).count() as i64)
+            .unwrap_or(0);
+
+        let department = departments.entry(department_name(&employee.dzial)).or_default();
+        department.0 += 1;
+        department.1 += dni_absencji;
+        company_days += dni_absencji;
+
+        let Some(days) = days else {
+            continue;
+        };
+        // Nowa nieobecność zaczyna się w każdym dniu, którego nie poprzedza dzień nieobecności
+        let liczba_nieobecnosci = days
+            .iter()
+            .filter(|date| !days.contains(&(**date - Duration::days(1))))
+            .count() as i64;
+
+        pracownicy.push(EmployeeStats {
+            nazwisko: employee.nazwisko.clone(),
+            imie: employee.imie.clone(),
+            pesel: employee.pesel.clone(),
+            dzial: employee.dzial.clone(),
+            liczba_nieobecnosci,
+            dni_absencji,
+            wspolczynnik_bradforda: liczba_nieobecnosci * liczba_nieobecnosci * dni_absencji,
+        });
+    }
+
+    pracownicy.sort_by(|a, b| {
+        b.wspolczynnik_bradforda
+            .cmp(&a.wspolczynnik_bradforda)
+            .then_with(|| a.nazwisko.cmp(&b.nazwisko))
+    });
+
+    Statistics {
+        okres_od: period_start,
+        okres_do: period_end,
+        dni_robocze,
+        firma: group_stats("Cała firma", employees.len(), company_days, dni_robocze),
+        dzialy: departments
+            .into_iter()
+            .map(|(nazwa, (count, days))| group_stats(nazwa, count, days, dni_robocze))
+            .collect(),
+        pracownicy,
+    }
+}
+
+pub fn write_sheet(
+    workbook: &mut Workbook,
+    stats: &Statistics,
+    privacy: &Privacy,
+    header_format: &Format,
+    date_format: &Format,
+) -> Result<()> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Statystyki")?;
+
+    let bold = Format::new().set_bold();
+    let plain = Format::new();
+    let percent_format = Format::new().set_num_format("0.00%");
+
+    sheet.set_column_width(0, 24.0)?;
+    sheet.set_column_width(1, 15.0)?;
+    sheet.set_column_width(2, 14.0)?;
+    sheet.set_column_width(3, 16.0)?;
+    sheet.set_column_width(4, 16.0)?;
+    sheet.set_column_width(5, 16.0)?;
+    sheet.set_column_width(6, 20.0)?;
+
+    sheet.write_string_with_format(0, 0, "Okres od", &bold)?;
+    sheet.write_number_with_format(0, 1, date_to_excel(stats.okres_od), date_format)?;
+    sheet.write_string_with_format(1, 0, "Okres do", &bold)?;
+    sheet.write_number_with_format(1, 1, date_to_excel(stats.okres_do), date_format)?;
+    sheet.write_string_with_format(2, 0, "Dni robocze w okresie", &bold)?;
+    sheet.write_number(2, 1, stats.dni_robocze as f64)?;
+
+    let group_headers = ["Grupa", "Pracownicy", "Dni dostępne", "Dni absencji", "Wskaźnik absencji"];
+    let mut row = 4;
+    for (col, header) in group_headers.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *header, header_format)?;
+    }
+    for group in std::iter::once(&stats.firma).chain(stats.dzialy.iter()) {
+        row += 1;
+        let name_format = if std::ptr::eq(group, &stats.firma) { &bold } else { &plain };
+        sheet.write_string_with_format(row, 0, &group.nazwa, name_format)?;
+        sheet.write_number(row, 1, group.pracownicy as f64)?;
+        sheet.write_number(row, 2, group.dni_dostepne as f64)?;
+        sheet.write_number(row, 3, group.dni_absencji as f64)?;
+        sheet.write_number_with_format(row, 4, group.wskaznik_absencji, &percent_format)?;
+    }
+
+    row += 2;
+    let employee_headers = [
+        "Nazwisko",
+        "Imię",
+        "PESEL",
+        "Dział",
+        "Nieobecności (S)",
+        "Dni absencji (D)",
+        "Współczynnik Bradforda",
+    ];
+    for (col, header) in employee_headers.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *header, header_format)?;
+    }
+    for employee in &stats.pracownicy {
+        row += 1;
+        sheet.write_string(row, 0, privacy.name(&employee.nazwisko))?;
+        sheet.write_string(row, 1, privacy.name(&employee.imie))?;
+        sheet.write_string(row, 2, privacy.pesel(&employee.pesel))?;
+        sheet.write_string(row, 3, &employee.dzial)?;
+        sheet.write_number(row, 4, employee.liczba_nieobecnosci as f64)?;
+        sheet.write_number(row, 5, employee.dni_absencji as f64)?;
+        sheet.write_number(row, 6, employee.wspolczynnik_bradforda as f64)?;
+    }
+
+    row += 2;
+    sheet.write_string(
+        row,
+        0,
+        "Wskaźnik absencji = dni robocze objęte zwolnieniami / (dni robocze w okresie × liczba pracowników). Współczynnik Bradforda = S² × D.",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn employee(nazwisko: &str, dzial: &str) -> EmployeeData {
+        EmployeeData {
+            nazwisko: nazwisko.to_string(),
+            pesel: format!("PESEL {}", nazwisko),
+            dzial: dzial.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn certificate(nazwisko: &str, start: NaiveDate, end: NaiveDate) -> EmployeeData {
+        EmployeeData {
+            data_od: Some(date_to_excel(start)),
+            data_do: Some(date_to_excel(end)),
+            ..employee(nazwisko, "")
+        }
+    }
+
+    // Listopad 2026: 20 dni roboczych - 21 dni od poniedziałku do piątku bez Święta Niepodległości (środa 11.11);
+    // Wszystkich Świętych (1.11) wypada w niedzielę
+    fn november() -> Statistics {
+        let employees = vec![employee("Adamska", "Kadry"), employee("Nowak", "Kadry"), employee("Zieliński", "")];
+        let mut cancelled = certificate("Nowak", date(11, 20), date(11, 27));
+        cancelled.status = "Anulowane".to_string();
+        let matched = vec![
+            // Zaczyna się przed okresem, nachodzi na kolejne zaświadczenie
+            certificate("Adamska", date(10, 28), date(11, 6)),
+            certificate("Adamska", date(11, 4), date(11, 12)),
+            // Dwie odrębne nieobecności po dwa dni robocze
+            certificate("Nowak", date(11, 9), date(11, 10)),
+            certificate("Nowak", date(11, 16), date(11, 17)),
+            cancelled,
+            // Po końcu okresu
+            certificate("Zieliński", date(12, 1), date(12, 5)),
+        ];
+        compute(&employees, &matched, date(11, 1), date(11, 30))
+    }
+
+    #[test]
+    fn weekends_and_holidays_are_not_working_days() {
+        let stats = november();
+        assert_eq!(stats.dni_robocze, 20);
+    }
+
+    #[test]
+    fn bradford_factor_counts_overlapping_certificates_once() {
+        let stats = november();
+        let rows: Vec<(&str, i64, i64, i64)> = stats
+            .pracownicy
+            .iter()
+            .map(|row| (row.nazwisko.as_str(), row.liczba_nieobecnosci, row.dni_absencji, row.wspolczynnik_bradforda))
+            .collect();
+        // Adamska: 2-6 i 9-12 listopada bez 11.11 - jedna nieobecność, 8 dni roboczych
+        assert_eq!(rows, [("Nowak", 2, 4, 16), ("Adamska", 1, 8, 8)]);
+    }
+
+    #[test]
+    fn absence_rate_is_clamped_to_the_period() {
+        let stats = november();
+        assert_eq!((stats.firma.pracownicy, stats.firma.dni_dostepne, stats.firma.dni_absencji), (3, 60, 12));
+        assert!((stats.firma.wskaznik_absencji - 0.2).abs() < 1e-9);
+
+        let departments: Vec<(&str, i64, i64)> = stats
+            .dzialy
+            .iter()
+            .map(|group| (group.nazwa.as_str(), group.dni_dostepne, group.dni_absencji))
+            .collect();
+        assert_eq!(departments, [(NO_DEPARTMENT, 20, 0), ("Kadry", 40, 12)]);
+        assert!((stats.dzialy[1].wskaznik_absencji - 0.3).abs() < 1e-9);
+    }
+}
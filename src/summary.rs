@@ -0,0 +1,196 @@
+use anyhow::Result;
+use chrono::{Datelike, Local};
+use rust_xlsxwriter::{Chart, Format, Worksheet};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::excel_merger::{EmployeeData, MatchedData};
+use crate::output::Report;
+use crate::statistics;
+
+pub const SHEET_NAME: &str = "Podsumowanie";
+const NO_STATUS: &str = "(brak statusu)";
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub pracownicy: usize,
+    pub wiersze_l4: usize,
+    pub dopasowane: usize,
+    pub osoby_dopasowane: usize,
+    pub niedopasowane: usize,
+    pub pominiete: usize,
+    // Liczba dopasowanych zaświadczeń według statusu
+    pub statusy: BTreeMap<String, usize>,
+    // (rok, miesiąc) -> (zaświadczenia rozpoczęte w miesiącu, dni zwolnień przypadające na miesiąc)
+    pub miesiace: BTreeMap<(i32, u32), (usize, i64)>,
+    // Dział -> (zaświadczenia, dni zwolnień)
+    pub dzialy: BTreeMap<String, (usize, i64)>,
+}
+
+pub fn compute(data: &MatchedData) -> Summary {
+    from_rows(data.employees.len(), &data.matched, data.unmatched.len(), data.skipped)
+}
+
+// Podsumowanie dopasowanych zaświadczeń (np. jednej grupy podziału raportu) z liczbami
+// pracowników oraz niedopasowanych i pominiętych wierszy L4
+pub fn from_rows(pracownicy: usize, matched: &[EmployeeData], niedopasowane: usize, pominiete: usize) -> Summary {
+    let mut summary = Summary {
+        pracownicy,
+        wiersze_l4: matched.len() + niedopasowane,
+        dopasowane: matched.len(),
+        osoby_dopasowane: matched.iter().map(|employee| employee.pesel.as_str()).collect::<HashSet<_>>().len(),
+        niedopasowane,
+        pominiete,
+        ..Default::default()
+    };
+
+    for employee in matched {
+        let status = employee.status.trim();
+        let status = if status.is_empty() { NO_STATUS } else { status };
+        *summary.statusy.entry(status.to_string()).or_insert(0) += 1;
+
+        // Anulowane zaświadczenia i błędne okresy nie wchodzą do zestawień dni
+        if employee.is_cancelled() {
+            continue;
+        }
+        let Some((start, end)) = employee.okres() else {
+            continue;
+        };
+        if start > end {
+            continue;
+        }
+
+        summary.miesiace.entry((start.year(), start.month())).or_default().0 += 1;
+        for date in start.iter_days().take_while(|date| *date <= end) {
+            summary.miesiace.entry((date.year(), date.month())).or_default().1 += 1;
+        }
+
+        let department = summary
+            .dzialy
+            .entry(statistics::department_name(&employee.dzial).to_string())
+            .or_default();
+        department.0 += 1;
+        department.1 += (end - start).num_days() + 1;
+    }
+
+    summary
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// Nagłówek tabeli i jej wiersze; zwraca numer ostatniego zapisanego wiersza
+fn write_table(
+    sheet: &mut Worksheet,
+    start_row: u32,
+    headers: &[&str],
+    rows: &[(String, Vec<f64>)],
+    header_format: &Format,
+) -> Result<u32> {
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(start_row, col as u16, *header, header_format)?;
+    }
+    let mut row = start_row;
+    for (label, values) in rows {
+        row += 1;
+        sheet.write_string(row, 0, label)?;
+        for (col, value) in values.iter().enumerate() {
+            sheet.write_number(row, col as u16 + 1, *value)?;
+        }
+    }
+    Ok(row)
+}
+
+pub fn write_sheet(sheet: &mut Worksheet, report: &Report, charts: bool, header_format: &Format) -> Result<()> {
+    let summary = report.summary;
+    let title_format = Format::new().set_bold().set_font_size(14);
+    let bold = Format::new().set_bold();
+
+    sheet.set_column_width(0, 30.0)?;
+    sheet.set_column_width(1, 16.0)?;
+    sheet.set_column_width(2, 14.0)?;
+    sheet.write_string_with_format(0, 0, "Podsumowanie raportu L4", &title_format)?;
+
+    let elapsed = (Local::now() - report.started).num_milliseconds() as f64 / 1000.0;
+    let info = [
+        ("Plik pracowników", file_name(report.first_file)),
+        ("Plik L4", file_name(report.second_file)),
+        ("Data wygenerowania", report.started.format("%d.%m.%Y %H:%M:%S").to_string()),
+        ("Czas przetwarzania", format!("{:.2} s", elapsed)),
+        ("Dane osobowe", report.privacy.label().to_string()),
+    ];
+    let mut row = 2;
+    for (label, value) in info {
+        sheet.write_string_with_format(row, 0, label, &bold)?;
+        sheet.write_string(row, 1, value)?;
+        row += 1;
+    }
+
+    row += 1;
+    let counts = [
+        ("Pracownicy na liście", summary.pracownicy),
+        ("Wiersze L4", summary.wiersze_l4),
+        ("Dopasowane zaświadczenia", summary.dopasowane),
+        ("Osoby z zaświadczeniami", summary.osoby_dopasowane),
+        ("Niedopasowane wiersze L4", summary.niedopasowane),
+        ("Pominięte wiersze", summary.pominiete),
+    ];
+    for (label, count) in counts {
+        sheet.write_string_with_format(row, 0, label, &bold)?;
+        sheet.write_number(row, 1, count as f64)?;
+        row += 1;
+    }
+
+    row += 1;
+    let statuses: Vec<(String, Vec<f64>)> = summary
+        .statusy
+        .iter()
+        .map(|(status, count)| (status.clone(), vec![*count as f64]))
+        .collect();
+    row = write_table(sheet, row, &["Status zaśw.", "Zaświadczenia"], &statuses, header_format)? + 2;
+
+    let month_start = row;
+    let months: Vec<(String, Vec<f64>)> = summary
+        .miesiace
+        .iter()
+        .map(|((year, month), (count, days))| (format!("{}-{:02}", year, month), vec![*count as f64, *days as f64]))
+        .collect();
+    let month_end = write_table(sheet, row, &["Miesiąc", "Zaświadczenia", "Dni zwolnień"], &months, header_format)?;
+    row = month_end + 2;
+
+    let department_start = row;
+    let departments: Vec<(String, Vec<f64>)> = summary
+        .dzialy
+        .iter()
+        .map(|(name, (count, days))| (name.clone(), vec![*count as f64, *days as f64]))
+        .collect();
+    let department_end = write_table(sheet, row, &["Dział", "Zaświadczenia", "Dni zwolnień"], &departments, header_format)?;
+
+    if charts {
+        if !months.is_empty() {
+            let mut chart = Chart::new_column();
+            chart.title().set_name("Dni zwolnień w miesiącach");
+            chart.legend().set_hidden();
+            chart
+                .add_series()
+                .set_categories((SHEET_NAME, month_start + 1, 0, month_end, 0))
+                .set_values((SHEET_NAME, month_start + 1, 2, month_end, 2));
+            sheet.insert_chart(2, 4, &chart)?;
+        }
+        if !departments.is_empty() {
+            let mut chart = Chart::new_bar();
+            chart.title().set_name("Dni zwolnień według działów");
+            chart.legend().set_hidden();
+            chart
+                .add_series()
+                .set_categories((SHEET_NAME, department_start + 1, 0, department_end, 0))
+                .set_values((SHEET_NAME, department_start + 1, 2, department_end, 2));
+            sheet.insert_chart(18, 4, &chart)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,560 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use umya_spreadsheet::{NumberingFormat, Spreadsheet, Style, Worksheet};
+
+use crate::excel_merger::EmployeeData;
+use crate::layout::{self, Field, LayoutColumn, ReportLayout};
+
+// Komórka, od której zaczynają się wiersze danych; jej styl (i styl komórek obok) mają kolejne wiersze
+pub const DATA_ANCHOR: &str = "{{dane}}";
+// Opcjonalna komórka, od której wpisywane są nagłówki kolumn układu
+pub const HEADER_ANCHOR: &str = "{{naglowki}}";
+const DEFAULT_DATE_FORMAT: &str = "dd/mm/yyyy";
+
+struct Anchor {
+    sheet: String,
+    col: u32,
+    row: u32,
+}
+
+fn find_anchor(book: &Spreadsheet, marker: &str) -> Option<Anchor> {
+    book.get_sheet_collection().iter().find_map(|sheet| {
+        sheet
+            .get_cell_collection()
+            .into_iter()
+            .find(|cell| cell.get_value().trim() == marker)
+            .map(|cell| Anchor {
+                sheet: sheet.get_name().to_string(),
+                col: *cell.get_coordinate().get_col_num(),
+                row: *cell.get_coordinate().get_row_num(),
+            })
+    })
+}
+
+pub fn read(path: &Path) -> Result<Spreadsheet> {
+    umya_spreadsheet::reader::xlsx::read(path)
+        .map_err(|e| anyhow!("Nie można otworzyć pliku {}: {:?}", path.display(), e))
+}
+
+// Skoroszyt zapisany przez umya-spreadsheet; source - plik, z którego go wczytano (szablon albo
+// skoroszyt, do którego dopisywany jest arkusz)
+pub fn to_bytes(book: &Spreadsheet, source: Option<&Path>) -> Result<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+    umya_spreadsheet::writer::xlsx::write_writer(book, &mut buffer)
+        .map_err(|e| anyhow!("Nie można zapisać skoroszytu: {:?}", e))?;
+    match source {
+        Some(source) => restore_bar_grouping(buffer.into_inner(), source),
+        None => Ok(buffer.into_inner()),
+    }
+}
+
+// Wartości <c:grouping> kolejnych wykresów słupkowych w części wykresu
+fn bar_groupings(xml: &str) -> Vec<&str> {
+    const OPEN: &str = "<c:barChart>";
+    const CLOSE: &str = "</c:barChart>";
+    const GROUPING: &str = "<c:grouping val=\"";
+    let mut groupings = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(OPEN) {
+        let Some(length) = rest[start..].find(CLOSE) else {
+            break;
+        };
+        let chart = &rest[start..start + length];
+        let value = chart
+            .find(GROUPING)
+            .map(|idx| &chart[idx + GROUPING.len()..])
+            .and_then(|value| value.find('"').map(|end| &value[..end]));
+        groupings.push(value.unwrap_or_default());
+        rest = &rest[start + length + CLOSE.len()..];
+    }
+    groupings
+}
+
+// Części XML skoroszytu (także relacje .rels): nazwa części -> treść
+fn xml_parts<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<HashMap<String, String>> {
+    let mut parts = HashMap::new();
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx)?;
+        if file.name().ends_with(".xml") || file.name().ends_with(".rels") {
+            let mut xml = String::new();
+            file.read_to_string(&mut xml)?;
+            parts.insert(file.name().to_string(), xml);
+        }
+    }
+    Ok(parts)
+}
+
+// Kolejne elementy <tag ...> (sam znacznik otwierający z atrybutami)
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}", tag);
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let element = &rest[start..];
+        let end = element.find('>').map_or(element.len(), |end| end + 1);
+        if element[open.len()..].starts_with([' ', '/', '>']) {
+            found.push(&element[..end]);
+        }
+        rest = &element[end..];
+    }
+    found
+}
+
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = format!(" {}=\"", name);
+    let start = element.find(&prefix)? + prefix.len();
+    let length = element[start..].find('"')?;
+    Some(&element[start..start + length])
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Relacje części: identyfikator -> (typ, ścieżka wskazanej części w archiwum)
+fn relationships(parts: &HashMap<String, String>, part: &str) -> HashMap<String, (String, String)> {
+    let (dir, name) = part.rsplit_once('/').unwrap_or(("", part));
+    let Some(rels) = parts.get(&format!("{}/_rels/{}.rels", dir, name)) else {
+        return HashMap::new();
+    };
+    elements(rels, "Relationship")
+        .into_iter()
+        .filter_map(|relationship| {
+            let id = attribute(relationship, "Id")?;
+            let kind = attribute(relationship, "Type").unwrap_or_default();
+            let target = unescape(attribute(relationship, "Target")?);
+            Some((id.to_string(), (kind.to_string(), resolve(dir, &target))))
+        })
+        .collect()
+}
+
+// Ścieżka z relacji względem katalogu części, np. ("xl/worksheets", "../drawings/drawing1.xml") -> "xl/drawings/drawing1.xml"
+fn resolve(dir: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut path: Vec<&str> = dir.split('/').filter(|segment| !segment.is_empty()).collect();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                path.pop();
+            }
+            "." | "" => {}
+            segment => path.push(segment),
+        }
+    }
+    path.join("/")
+}
+
+// Części wykresów według arkusza i kolejności wykresu na rysunku arkusza, np.
+// ("Podsumowanie", 0) -> "xl/charts/chart1.xml"; numeracja części po ponownym zapisie może być inna
+fn chart_locations(parts: &HashMap<String, String>) -> HashMap<(String, usize), String> {
+    let mut locations = HashMap::new();
+    let Some(workbook) = parts.get("xl/workbook.xml") else {
+        return locations;
+    };
+    let workbook_relationships = relationships(parts, "xl/workbook.xml");
+    for sheet in elements(workbook, "sheet") {
+        let (Some(name), Some(id)) = (attribute(sheet, "name"), attribute(sheet, "r:id")) else {
+            continue;
+        };
+        let Some((_, sheet_part)) = workbook_relationships.get(id) else {
+            continue;
+        };
+        for (kind, drawing) in relationships(parts, sheet_part).values() {
+            let Some(drawing_xml) = parts.get(drawing).filter(|_| kind.ends_with("/drawing")) else {
+                continue;
+            };
+            let drawing_relationships = relationships(parts, drawing);
+            let charts = elements(drawing_xml, "c:chart")
+                .into_iter()
+                .filter_map(|chart| drawing_relationships.get(attribute(chart, "r:id")?));
+            for (idx, (_, chart)) in charts.enumerate() {
+                locations.insert((unescape(name), idx), chart.clone());
+            }
+        }
+    }
+    locations
+}
+
+// umya-spreadsheet nie zna grupowania "clustered" wykresów słupkowych i zapisuje je jako "percentStacked".
+// Grupowanie jest przywracane tylko w wykresach, które były w pliku źródłowym - według wartości z tego
+// pliku, więc wykresy 100% użytkownika pozostają bez zmian. Wykres zapisanego skoroszytu jest łączony
+// z wykresem pliku źródłowego przez arkusz i położenie na jego rysunku (relacje arkusz -> rysunek -> wykres),
+// bo umya-spreadsheet może zmienić numerację części wykresów. Pozostałe części są kopiowane bez zmian.
+fn restore_bar_grouping(bytes: Vec<u8>, source: &Path) -> Result<Vec<u8>> {
+    let file = std::fs::File::open(source).with_context(|| format!("Nie można otworzyć pliku {}", source.display()))?;
+    let original = xml_parts(&mut zip::ZipArchive::new(file)?)?;
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let written = xml_parts(&mut archive)?;
+
+    let original_charts = chart_locations(&original);
+    let sources: HashMap<String, &str> = chart_locations(&written)
+        .into_iter()
+        .filter_map(|(location, part)| Some((part, original.get(original_charts.get(&location)?)?.as_str())))
+        .collect();
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for idx in 0..archive.len() {
+        let file = archive.by_index(idx)?;
+        let (Some(original), Some(xml)) = (sources.get(file.name()), written.get(file.name())) else {
+            writer.raw_copy_file(file)?;
+            continue;
+        };
+        writer.start_file(file.name().to_string(), zip::write::SimpleFileOptions::default())?;
+        writer.write_all(with_groupings(xml, &bar_groupings(original)).as_bytes())?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+// Część wykresu z grupowaniem wykresów słupkowych z pliku źródłowego; przy innej liczbie
+// wykresów słupkowych (wykres zmieniony od zapisu pliku źródłowego) - bez zmian
+fn with_groupings(xml: &str, groupings: &[&str]) -> String {
+    let written = bar_groupings(xml);
+    if written.len() != groupings.len() || written == groupings {
+        return xml.to_string();
+    }
+    const OPEN: &str = "<c:barChart>";
+    const CLOSE: &str = "</c:barChart>";
+    let mut result = String::with_capacity(xml.len());
+    let mut rest = xml;
+    for (current, original) in written.iter().zip(groupings) {
+        let Some(start) = rest.find(OPEN) else {
+            break;
+        };
+        let Some(length) = rest[start..].find(CLOSE) else {
+            break;
+        };
+        let end = start + length + CLOSE.len();
+        result.push_str(&rest[..start]);
+        result.push_str(&rest[start..end].replace(
+            &format!("<c:grouping val=\"{}\"/>", current),
+            &format!("<c:grouping val=\"{}\"/>", original),
+        ));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Kopia szablonu z wierszami danych wstawionymi w miejscu znacznika {{dane}} i podstawionymi
+// znacznikami tekstowymi, np. ("{{okres}}", "01.10.2026 - 31.10.2026")
+pub fn fill(
+    template: &Path,
+    layout: &ReportLayout,
+    data: &[EmployeeData],
+    placeholders: &[(&str, String)],
+) -> Result<Spreadsheet> {
+    let mut book = read(template)?;
+    let Some(anchor) = find_anchor(&book, DATA_ANCHOR) else {
+        bail!("Szablon {} nie zawiera znacznika {}", template.display(), DATA_ANCHOR);
+    };
+    let header = find_anchor(&book, HEADER_ANCHOR);
+
+    // Wiersze poniżej znacznika (sumy, stopka) przesuwają się w dół razem z formułami
+    if data.len() > 1 {
+        book.insert_new_row(&anchor.sheet, &(anchor.row + 1), &(data.len() as u32 - 1));
+    }
+
+    let sheet = book
+        .get_sheet_by_name_mut(&anchor.sheet)
+        .context("Nie znaleziono arkusza ze znacznikiem danych")?;
+
+    if let Some(header) = header.filter(|header| header.sheet == anchor.sheet) {
+        for (offset, column) in layout.kolumny.iter().enumerate() {
+            let col = header.col + offset as u32;
+            let style = sheet.get_style((header.col, header.row)).clone();
+            let cell = sheet.get_cell_mut((col, header.row));
+            cell.set_value_string(column.naglowek.clone());
+            cell.set_style(style);
+        }
+    }
+
+    let styles: Vec<Style> = (0..layout.kolumny.len())
+        .map(|offset| sheet.get_style((anchor.col + offset as u32, anchor.row)).clone())
+        .collect();
+    sheet.get_cell_mut((anchor.col, anchor.row)).set_value_string("");
+
+    for (idx, employee) in data.iter().enumerate() {
+        let row = anchor.row + idx as u32;
+        for (offset, (column, style)) in layout.kolumny.iter().zip(&styles).enumerate() {
+            write_cell(sheet, anchor.col + offset as u32, row, employee, column, style.clone());
+        }
+    }
+
+    for sheet in book.get_sheet_collection_mut() {
+        replace_placeholders(sheet, placeholders);
+    }
+    Ok(book)
+}
+
+fn write_cell(sheet: &mut Worksheet, col: u32, row: u32, employee: &EmployeeData, column: &LayoutColumn, mut style: Style) {
+    let is_general = style
+        .get_number_format()
+        .is_none_or(|format| format.get_format_code() == NumberingFormat::FORMAT_GENERAL);
+    let custom_format = column.format.trim();
+
+    let cell = sheet.get_cell_mut((col, row));
+    match &column.pole {
+        Field::DataWystawienia | Field::DataOd | Field::DataDo => {
+            // Data bez formatu w szablonie byłaby widoczna jako liczba
+            if is_general || !custom_format.is_empty() {
+                let format = if custom_format.is_empty() { DEFAULT_DATE_FORMAT } else { custom_format };
+                style.get_number_format_mut().set_format_code(format);
+            }
+            match layout::date_value(employee, &column.pole) {
+                Some(value) => cell.set_value_number(value),
+                None => cell.set_value_string(""),
+            };
+        }
+        Field::Dni => {
+            if !custom_format.is_empty() {
+                style.get_number_format_mut().set_format_code(custom_format);
+            }
+            match layout::days(employee) {
+                Some(days) => cell.set_value_number(days as f64),
+                None => cell.set_value_string(""),
+            };
+        }
+        field => {
+            cell.set_value_string(layout::text_value(employee, field));
+        }
+    }
+    cell.set_style(style);
+}
+
+fn replace_placeholders(sheet: &mut Worksheet, placeholders: &[(&str, String)]) {
+    for cell in sheet.get_cell_collection_mut() {
+        if cell.is_formula() {
+            continue;
+        }
+        let value = cell.get_value().to_string();
+        if !value.contains("{{") {
+            continue;
+        }
+        let replaced = placeholders
+            .iter()
+            .fold(value.clone(), |text, (marker, replacement)| text.replace(marker, replacement));
+        if replaced != value {
+            cell.set_value_string(replaced);
+        }
+    }
+}
+
+fn read_generated(bytes: &[u8]) -> Result<Spreadsheet> {
+    umya_spreadsheet::reader::xlsx::read_reader(Cursor::new(bytes), true)
+        .map_err(|e| anyhow!("Nie można odczytać wygenerowanego skoroszytu: {:?}", e))
+}
+
+// Dołącza arkusze z drugiego skoroszytu (np. wygenerowanego przez rust_xlsxwriter);
+// zwraca nazwy arkuszy pominiętych, bo istnieją już w skoroszycie docelowym
+pub fn copy_sheets(book: &mut Spreadsheet, source: &[u8], skip: &[&str]) -> Result<Vec<String>> {
+    let source = read_generated(source)?;
+    let mut skipped = Vec::new();
+    for sheet in source.get_sheet_collection() {
+        if skip.contains(&sheet.get_name()) {
+            continue;
+        }
+        if book.get_sheet_by_name(sheet.get_name()).is_some() {
+            skipped.push(sheet.get_name().to_string());
+            continue;
+        }
+        add_sheet(book, sheet.clone())?;
+    }
+    Ok(skipped)
+}
+
+// Nazwy tabel Excela muszą być niepowtarzalne w całym skoroszycie
+pub fn add_sheet(book: &mut Spreadsheet, mut sheet: Worksheet) -> Result<()> {
+    let mut used: Vec<String> = book
+        .get_sheet_collection()
+        .iter()
+        .flat_map(|existing| existing.get_tables().iter().map(|table| table.get_name().to_lowercase()))
+        .collect();
+    let base: String = sheet
+        .get_name()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    for table in sheet.get_tables_mut() {
+        let mut name = format!("Tabela_{}", base);
+        let mut counter = 2;
+        while used.contains(&name.to_lowercase()) {
+            name = format!("Tabela_{}_{}", base, counter);
+            counter += 1;
+        }
+        table.set_name(&name);
+        table.set_display_name(&name);
+        used.push(name.to_lowercase());
+    }
+    for view in sheet.get_sheet_views_mut().get_sheet_view_list_mut() {
+        view.set_tab_selected(false);
+    }
+
+    let name = sheet.get_name().to_string();
+    book.add_sheet(sheet)
+        .map_err(|e| anyhow!("Nie można dodać arkusza {}: {}", name, e))?;
+    Ok(())
+}
+
+// Części skoroszytu, których umya-spreadsheet może nie zachować przy ponownym zapisie
+const PRESERVED_PARTS: [(&str, &str); 8] = [
+    ("xl/pivotTables/", "tabele przestawne"),
+    ("xl/pivotCache/", "dane tabel przestawnych"),
+    ("xl/externalLinks/", "łącza do innych skoroszytów"),
+    ("xl/vbaProject.bin", "makra"),
+    ("xl/charts/chart", "wykresy"),
+    ("xl/drawings/drawing", "rysunki"),
+    ("xl/comments", "komentarze"),
+    ("xl/media/", "obrazy"),
+];
+
+fn part_counts(names: impl Iterator<Item = String>) -> [usize; PRESERVED_PARTS.len()] {
+    let mut counts = [0; PRESERVED_PARTS.len()];
+    for name in names {
+        if let Some(idx) = PRESERVED_PARTS.iter().position(|(prefix, _)| name.starts_with(prefix)) {
+            counts[idx] += 1;
+        }
+    }
+    counts
+}
+
+// Błąd, jeśli w skoroszycie zapisanym przez umya-spreadsheet brakuje części istniejącego pliku
+// (np. tabel przestawnych czy makr) - plik nie jest wtedy nadpisywany
+pub fn ensure_preserved(original: &Path, written: &[u8]) -> Result<()> {
+    let file = std::fs::File::open(original)
+        .with_context(|| format!("Nie można otworzyć pliku {}", original.display()))?;
+    let before = part_counts(zip::ZipArchive::new(file)?.file_names().map(str::to_string));
+    let after = part_counts(zip::ZipArchive::new(Cursor::new(written))?.file_names().map(str::to_string));
+    let lost: Vec<&str> = PRESERVED_PARTS
+        .iter()
+        .zip(before.iter().zip(after))
+        .filter(|(_, (before, after))| after < *before)
+        .map(|((_, label), _)| *label)
+        .collect();
+    if !lost.is_empty() {
+        bail!(
+            "Dopisanie arkusza usunęłoby z pliku {}: {} - plik pozostawiono bez zmian",
+            original.display(),
+            lost.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// Kopia pliku sprzed dopisania arkusza, np. zestawienie.xlsx.bak
+pub fn backup(path: &Path) -> Result<PathBuf> {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".bak");
+    let backup = PathBuf::from(name);
+    std::fs::copy(path, &backup).with_context(|| format!("Nie można utworzyć kopii {}", backup.display()))?;
+    Ok(backup)
+}
+
+// Skoroszyt z pliku wynikowego (albo nowy, jeśli plik nie istnieje) z dopisanym arkuszem
+// source_sheet wygenerowanego raportu pod nazwą sheet_name; pozostałe arkusze pozostają bez zmian
+pub fn append_sheet(
+    path: &Path,
+    generated: &[u8],
+    source_sheet: &str,
+    sheet_name: &str,
+    replace: bool,
+) -> Result<Spreadsheet> {
+    let mut book = if path.exists() { read(path)? } else { umya_spreadsheet::new_file_empty_worksheet() };
+    let source = read_generated(generated)?;
+    let mut sheet = source
+        .get_sheet_by_name(source_sheet)
+        .with_context(|| format!("Brak arkusza {} w wygenerowanym raporcie", source_sheet))?
+        .clone();
+    sheet.set_name(sheet_name);
+
+    // Excel nie rozróżnia wielkości liter w nazwach arkuszy
+    let position = book
+        .get_sheet_collection()
+        .iter()
+        .position(|existing| existing.get_name().to_lowercase() == sheet_name.to_lowercase());
+    if let Some(position) = position {
+        if !replace {
+            bail!("Arkusz {} już istnieje w pliku {}", sheet_name, path.display());
+        }
+        book.remove_sheet(position)
+            .map_err(|e| anyhow!("Nie można usunąć arkusza {}: {}", sheet_name, e))?;
+    }
+    add_sheet(&mut book, sheet)?;
+
+    // Zastąpiony arkusz zostaje na swoim miejscu
+    if let Some(position) = position {
+        let sheets = book.get_sheet_collection_mut();
+        if let Some(sheet) = sheets.pop() {
+            sheets.insert(position, sheet);
+        }
+    }
+    Ok(book)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Skoroszyt z arkuszem "Wykresy & dane" i dwoma wykresami na rysunku, zapisanymi w podanych częściach
+    fn parts(charts: [&str; 2]) -> HashMap<String, String> {
+        let relationship = |id: &str, kind: &str, target: &str| {
+            format!(r#"<Relationship Id="{}" Type="http://schemas/{}" Target="{}"/>"#, id, kind, target)
+        };
+        HashMap::from([
+            (
+                "xl/workbook.xml".to_string(),
+                r#"<workbook><sheets><sheet name="Dane" sheetId="1" r:id="rId1"/><sheet name="Wykresy &amp; dane" sheetId="2" r:id="rId2"/></sheets></workbook>"#.to_string(),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels".to_string(),
+                relationship("rId1", "worksheet", "worksheets/sheet1.xml")
+                    + &relationship("rId2", "worksheet", "/xl/worksheets/sheet2.xml"),
+            ),
+            ("xl/worksheets/_rels/sheet2.xml.rels".to_string(), relationship("rId1", "drawing", "../drawings/drawing1.xml")),
+            (
+                "xl/drawings/drawing1.xml".to_string(),
+                r#"<xdr:wsDr><c:chart r:id="rId7"/><c:chart r:id="rId3"/></xdr:wsDr>"#.to_string(),
+            ),
+            (
+                "xl/drawings/_rels/drawing1.xml.rels".to_string(),
+                relationship("rId3", "chart", &format!("../charts/{}", charts[1]))
+                    + &relationship("rId7", "chart", &format!("../charts/{}", charts[0])),
+            ),
+        ])
+    }
+
+    #[test]
+    fn charts_are_located_by_sheet_and_drawing_order() {
+        let locations = chart_locations(&parts(["chart1.xml", "chart2.xml"]));
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[&("Wykresy & dane".to_string(), 0)], "xl/charts/chart1.xml");
+        assert_eq!(locations[&("Wykresy & dane".to_string(), 1)], "xl/charts/chart2.xml");
+
+        // Po ponownym zapisie numeracja części może być inna, położenie wykresu - nie
+        let renumbered = chart_locations(&parts(["chart2.xml", "chart1.xml"]));
+        assert_eq!(renumbered[&("Wykresy & dane".to_string(), 0)], "xl/charts/chart2.xml");
+    }
+
+    #[test]
+    fn groupings_are_restored_only_for_the_same_number_of_bar_charts() {
+        let written = r#"<c:barChart><c:grouping val="percentStacked"/></c:barChart>"#;
+        let restored = with_groupings(written, &["clustered"]);
+        assert_eq!(restored, r#"<c:barChart><c:grouping val="clustered"/></c:barChart>"#);
+        assert_eq!(with_groupings(written, &["clustered", "stacked"]), written);
+    }
+
+    #[test]
+    fn relationship_targets_are_resolved_against_the_part_directory() {
+        assert_eq!(resolve("xl/worksheets", "../drawings/drawing1.xml"), "xl/drawings/drawing1.xml");
+        assert_eq!(resolve("xl", "worksheets/sheet1.xml"), "xl/worksheets/sheet1.xml");
+        assert_eq!(resolve("xl/worksheets", "/xl/drawings/drawing2.xml"), "xl/drawings/drawing2.xml");
+    }
+}